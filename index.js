@@ -1,4 +1,18 @@
-import init, { main } from "./pkg/spatio.js";
+import init, { main, set_on_click, set_on_hover } from "./pkg/spatio.js";
 init().then(() => {
-  main()
+  try {
+    main();
+  } catch (error) {
+    // The page already shows the message in place of the canvas
+    console.error(error);
+    return;
+  }
+
+  const canvas = document.getElementById("canvas");
+  set_on_hover((event) => {
+    canvas.style.cursor = event ? "pointer" : "default";
+  });
+  set_on_click((event) => {
+    console.log(`object ${event.object_id}, face ${event.face}, vertex ${event.vertex}`);
+  });
 });
@@ -0,0 +1,291 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use web_sys::wasm_bindgen::prelude::*;
+use web_sys::{
+    window,
+    Document,
+    HtmlCanvasElement,
+    HtmlInputElement,
+    MouseEvent,
+};
+
+use crate::backend::Backend;
+use crate::ball::Ball;
+use crate::camera::{Camera, Projection};
+use crate::cartesian_axis::CartesianAxis;
+use crate::colour::Colour;
+use crate::error::SpatioError;
+use crate::label::{Font, Label};
+use crate::material::Material;
+use crate::orbit::OrbitControls;
+use crate::picking::Picker;
+use crate::program::Program;
+use crate::scene::{Scene, SceneNode};
+use crate::vector::{Vec3, Vertex};
+use crate::webgl::{WebGl1Backend, WebGl2Backend};
+use crate::{input, log, utils};
+
+#[wasm_bindgen]
+pub fn main() -> Result<(), SpatioError> {
+    let document = window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| SpatioError::Dom(String::from("No document")))?;
+    let canvas = document
+        .get_element_by_id("canvas")
+        .and_then(|canvas| canvas.dyn_into::<HtmlCanvasElement>().ok())
+        .ok_or_else(|| SpatioError::Dom(String::from("No canvas")))?;
+
+    // Whatever went wrong, the page says so instead of showing a blank canvas
+    let result = start(&document, &canvas);
+    if let Err(error) = &result {
+        utils::show_error(&document, &canvas, &error.to_string());
+    }
+
+    return result;
+}
+
+fn start(
+    document: &Document,
+    canvas: &HtmlCanvasElement,
+) -> Result<(), SpatioError> {
+    let mut backend = init(canvas)?;
+
+    let program = Rc::new(Program::standard(backend.as_mut())?);
+
+    return run(document, canvas, backend, program);
+}
+
+// WebGL2 where available, WebGL1 otherwise
+fn init(canvas: &HtmlCanvasElement) -> Result<Box<dyn Backend>, SpatioError> {
+    let unsupported = || {
+        SpatioError::ContextCreation(String::from(
+            "This browser doesn't support WebGL",
+        ))
+    };
+
+    let mut backend: Box<dyn Backend> =
+        if let Some(context) = canvas.get_context("webgl2")? {
+            Box::new(WebGl2Backend::new(
+                context.dyn_into().map_err(|_| unsupported())?,
+            ))
+        } else if let Some(context) = canvas.get_context("webgl")? {
+            Box::new(WebGl1Backend::new(
+                context.dyn_into().map_err(|_| unsupported())?,
+            ))
+        } else {
+            return Err(unsupported());
+        };
+
+    backend.set_viewport(canvas.width(), canvas.height());
+    return Ok(backend);
+}
+
+fn run(
+    document: &Document,
+    canvas: &HtmlCanvasElement,
+    mut backend: Box<dyn Backend>,
+    program: Rc<Program>,
+) -> Result<(), SpatioError> {
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 3.0), Vec3::ZERO);
+    camera.near = 1.0;
+    camera.far = 2000.0;
+    camera.set_viewport(canvas.width(), canvas.height());
+    let window =
+        window().ok_or_else(|| SpatioError::Dom(String::from("No window")))?;
+    camera.set_pixel_ratio(window.device_pixel_ratio() as f32);
+    let camera = Rc::new(RefCell::new(camera));
+
+    let controls =
+        Rc::new(RefCell::new(OrbitControls::from_camera(&camera.borrow())));
+    let dragged =
+        input::attach_mouse_controls(canvas, controls.clone(), camera.clone())?;
+    input::attach_touch_controls(canvas, controls.clone(), camera.clone())?;
+
+    {
+        let camera = camera.clone();
+
+        utils::create_input_handler_f32(
+            document,
+            "slider_fov",
+            Box::new(move |slider: &HtmlInputElement| {
+                camera.borrow_mut().fov = slider.value_as_number() as f32;
+            }),
+        )?;
+    }
+
+    {
+        let camera = camera.clone();
+
+        utils::create_input_handler_f32(
+            document,
+            "slider_near",
+            Box::new(move |slider: &HtmlInputElement| {
+                camera.borrow_mut().near = slider.value_as_number() as f32;
+            }),
+        )?;
+    }
+
+    {
+        let camera = camera.clone();
+
+        utils::create_input_handler_f32(
+            document,
+            "slider_far",
+            Box::new(move |slider: &HtmlInputElement| {
+                camera.borrow_mut().far = slider.value_as_number() as f32;
+            }),
+        )?;
+    }
+
+    {
+        let camera = camera.clone();
+
+        utils::create_input_handler_f32(
+            document,
+            "toggle_ortho",
+            Box::new(move |checkbox: &HtmlInputElement| {
+                camera.borrow_mut().projection = if checkbox.checked() {
+                    Projection::Orthographic
+                } else {
+                    Projection::Perspective
+                };
+            }),
+        )?;
+    }
+
+    let b = Rc::new(Ball::new(
+        backend.as_mut(),
+        Vertex::new(0.0, 0.0, 0.0),
+        1.0,
+        20,
+        Rc::new(Material::new(program.clone(), Colour::RED.with_alpha(0.4))?),
+    )?);
+
+    // The axes show through the translucent ball
+    let mut scene = Scene::new();
+    scene.add(SceneNode::with_drawable(b.clone()).with_id(0));
+    let billboard = Rc::new(Program::billboard(backend.as_mut())?);
+    let font = Font::default().bold();
+    let mut label = |text, colour| {
+        Label::text(
+            backend.as_mut(),
+            billboard.clone(),
+            Vertex::new(0.0, 0.0, 0.0),
+            text,
+            &font,
+            colour,
+        )
+    };
+    let labels = [
+        label("x", Colour::RED)?,
+        label("y", Colour::GREEN)?,
+        label("z", Colour::BLUE)?,
+    ];
+    scene.add(
+        CartesianAxis::new(backend.as_mut(), program)?
+            .with_labels(labels)
+            .into_node(),
+    );
+
+    let picker = Rc::new(RefCell::new(Picker::new()));
+    picker.borrow_mut().add(0, b.clone());
+
+    {
+        let camera = camera.clone();
+        let picker = picker.clone();
+        let canvas_ = canvas.clone();
+        let dragged = dragged.clone();
+
+        utils::create_mouse_handler(
+            canvas,
+            "click",
+            Box::new(move |event: MouseEvent| {
+                // The click ending a rotation isn't meant for the object
+                if *dragged.borrow() {
+                    return;
+                }
+
+                let (x, y) = utils::canvas_mouse_position(&canvas_, &event);
+                if let Some(ray) = camera.borrow().screen_ray(x, y) {
+                    picker.borrow().click(&ray);
+                }
+            }),
+        )?;
+    }
+
+    {
+        let camera = camera.clone();
+        let picker = picker.clone();
+        let canvas_ = canvas.clone();
+
+        utils::create_mouse_handler(
+            canvas,
+            "mousemove",
+            Box::new(move |event: MouseEvent| {
+                let (x, y) = utils::canvas_mouse_position(&canvas_, &event);
+                if let Some(ray) = camera.borrow().screen_ray(x, y) {
+                    picker.borrow_mut().hover(&ray);
+                }
+            }),
+        )?;
+    }
+
+    let draw_routine = Rc::new(RefCell::new(None));
+    let draw_routine_launcher = draw_routine.clone();
+
+    // Draw loop
+    {
+        let camera = camera.clone();
+        let controls = controls.clone();
+        let picker = picker.clone();
+
+        let canvas = canvas.clone();
+        let window = window.clone();
+
+        let mut last_timestamp = None;
+
+        *draw_routine_launcher.borrow_mut() =
+            Some(Closure::<dyn FnMut(f64)>::new(move |timestamp: f64| {
+                // Timestamps are in milliseconds
+                let dt = match last_timestamp {
+                    Some(last) => ((timestamp - last) / 1000.0) as f32,
+                    None => 0.0,
+                };
+                last_timestamp = Some(timestamp);
+
+                if let Some((width, height)) = utils::resize_canvas(&canvas) {
+                    backend.set_viewport(width, height);
+                    let mut camera = camera.borrow_mut();
+                    camera.set_viewport(width, height);
+                    // Changes when the window moves to another screen or
+                    // the page is zoomed
+                    camera.set_pixel_ratio(window.device_pixel_ratio() as f32);
+                }
+
+                controls.borrow_mut().update(dt, &mut camera.borrow_mut());
+
+                scene.update_world_matrices();
+                picker.borrow_mut().update_from_scene(&scene);
+
+                backend.clear(Colour::BLACK);
+
+                if let Err(error) =
+                    scene.draw(backend.as_mut(), &camera.borrow())
+                {
+                    log(&error.to_string());
+                }
+
+                utils::request_animation_frame(
+                    draw_routine.borrow().as_ref().unwrap(),
+                );
+            }));
+    }
+
+    // Launch the loop
+    utils::request_animation_frame(
+        draw_routine_launcher.borrow().as_ref().unwrap(),
+    );
+
+    return Ok(());
+}
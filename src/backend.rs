@@ -0,0 +1,370 @@
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "web")]
+use web_sys::{HtmlCanvasElement, HtmlImageElement};
+
+use crate::colour::Colour;
+use crate::error::SpatioError;
+use crate::image::Image;
+use crate::matrix::{Matrix3F, Matrix4F};
+use crate::vector::Vec3;
+
+// Opaque handles to resources owned by a backend. They only mean something to
+// the backend that handed them out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BufferHandle(pub u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProgramHandle(pub u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(pub u32);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BufferData<'a> {
+    // Packed vertex attributes, see `Mesh`
+    Vertices(&'a [f32]),
+    Indices(&'a [u16]),
+}
+
+// Where a texture's pixels come from. Whatever the source, the first row is
+// the top of the image, which ends up at v = 1.
+#[derive(Clone, Copy, Debug)]
+pub enum TextureData<'a> {
+    // 4 bytes per pixel, non-premultiplied
+    Rgba {
+        width:  u32,
+        height: u32,
+        pixels: &'a [u8],
+    },
+    // Has to be loaded already
+    #[cfg(feature = "web")]
+    HtmlImage(&'a HtmlImageElement),
+    #[cfg(feature = "web")]
+    Canvas(&'a HtmlCanvasElement),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Uniform {
+    Int(i32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3(Vec3),
+    Vec4([f32; 4]),
+    Mat3(Matrix3F),
+    Mat4(Matrix4F),
+}
+
+// GLSL types a uniform can be declared with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UniformType {
+    Int,
+    Bool,
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    Mat3,
+    Mat4,
+    Sampler2D,
+}
+
+// Which shader a program runs. Backends that can't run GLSL, like the
+// software one, emulate each of the built-in ones, and custom programs as if
+// they were the standard one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProgramKind {
+    Standard,
+    Unlit,
+    Billboard,
+    Polyline,
+    #[default]
+    Custom,
+}
+
+// What a linked program declares: its uniforms with their types and its
+// vertex attributes. Each element of an array uniform is listed as `name[i]`,
+// the array itself as `name` like GL does.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProgramInfo {
+    pub kind:       ProgramKind,
+    pub uniforms:   HashMap<String, UniformType>,
+    pub attributes: HashSet<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawMode {
+    Triangles,
+    Lines,
+}
+
+// A side of the triangles, front being the one they're counter-clockwise
+// from on screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Face {
+    Front,
+    Back,
+}
+
+// Everything primitives need from a graphics API. Shaders are written in GLSL
+// ES 3.00; backends that can't run them as is translate them or, like the
+// software one, emulate them by the program's kind. Attributes and uniforms
+// are referred to by name and silently ignored when the current program
+// doesn't declare them, like GL does with a null location. `Program` is the
+// checked way to set them.
+pub trait Backend {
+    fn create_buffer(
+        &mut self,
+        data: BufferData,
+    ) -> Result<BufferHandle, SpatioError>;
+
+    fn delete_buffer(&mut self, buffer: BufferHandle);
+
+    fn create_texture(
+        &mut self,
+        data: TextureData,
+    ) -> Result<TextureHandle, SpatioError>;
+
+    fn delete_texture(&mut self, texture: TextureHandle);
+
+    // Makes `texture` the one read by samplers set to `unit`
+    fn bind_texture(&mut self, unit: u32, texture: TextureHandle);
+
+    fn create_program(
+        &mut self,
+        kind: ProgramKind,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<ProgramHandle, SpatioError>;
+
+    fn use_program(&mut self, program: ProgramHandle);
+
+    // What the program declares, None for an unknown handle
+    fn program_info(&self, program: ProgramHandle) -> Option<ProgramInfo>;
+
+    // `size` is the number of floats per vertex
+    fn bind_attribute(&mut self, name: &str, buffer: BufferHandle, size: u32);
+
+    // The shader then reads the attribute's constant value
+    fn disable_attribute(&mut self, name: &str);
+
+    fn set_uniform(&mut self, name: &str, value: Uniform);
+
+    // Draws `count` vertices, looked up through `indices` when given
+    fn draw(
+        &mut self,
+        mode: DrawMode,
+        indices: Option<BufferHandle>,
+        count: usize,
+    );
+
+    fn set_viewport(&mut self, width: u32, height: u32);
+
+    // Off by default. When on, fragments are blended over what's already
+    // drawn by their alpha, non-premultiplied.
+    fn set_blending(&mut self, enabled: bool);
+
+    // On by default. Fragments are still depth tested when it's off.
+    fn set_depth_write(&mut self, enabled: bool);
+
+    // Off by default. When on, triangles are pushed slightly away from the
+    // camera, so lines drawn along their edges win the depth test.
+    fn set_depth_offset(&mut self, enabled: bool);
+
+    // On by default. When off, everything drawn ends up on top of what's
+    // already there.
+    fn set_depth_test(&mut self, enabled: bool);
+
+    // None by default. Triangles showing the given face to the camera aren't
+    // drawn.
+    fn set_face_culling(&mut self, face: Option<Face>);
+
+    // Clears depth along with colour, and turns depth writes back on
+    fn clear(&mut self, colour: Colour);
+}
+
+impl<'a> From<&'a Image> for TextureData<'a> {
+    fn from(image: &'a Image) -> TextureData<'a> {
+        TextureData::Rgba {
+            width:  image.width,
+            height: image.height,
+            pixels: &image.pixels,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl TextureData<'_> {
+    // Fails on an empty texture, or bytes that don't match the size
+    pub fn check(&self) -> Result<(), SpatioError> {
+        let (width, height) = self.size();
+        if width == 0 || height == 0 {
+            return Err(SpatioError::TextureCreation(String::from(
+                "Empty texture",
+            )));
+        }
+
+        // DOM elements always hold as many pixels as their size
+        let bytes = match self {
+            TextureData::Rgba {
+                pixels, ..
+            } => pixels.len(),
+            #[cfg(feature = "web")]
+            _ => return Ok(()),
+        };
+        let expected = width as usize * height as usize * 4;
+        if bytes != expected {
+            return Err(SpatioError::TextureCreation(format!(
+                "Expected {} bytes for a {}x{} texture, got {}",
+                expected, width, height, bytes
+            )));
+        }
+
+        return Ok(());
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        match self {
+            TextureData::Rgba {
+                width,
+                height,
+                ..
+            } => (*width, *height),
+            #[cfg(feature = "web")]
+            TextureData::HtmlImage(image) => {
+                (image.natural_width(), image.natural_height())
+            }
+            #[cfg(feature = "web")]
+            TextureData::Canvas(canvas) => (canvas.width(), canvas.height()),
+        }
+    }
+}
+
+impl From<Colour> for Uniform {
+    fn from(colour: Colour) -> Uniform {
+        Uniform::Vec4([colour.r, colour.g, colour.b, colour.a])
+    }
+}
+
+#[allow(dead_code)]
+impl Uniform {
+    // Whether the value can be set on a uniform declared as `uniform_type`.
+    // Booleans and samplers are set through integers, like in GL.
+    pub fn fits(&self, uniform_type: UniformType) -> bool {
+        matches!(
+            (self, uniform_type),
+            (
+                Uniform::Int(_),
+                UniformType::Int | UniformType::Bool | UniformType::Sampler2D
+            ) | (Uniform::Float(_), UniformType::Float) |
+                (Uniform::Vec2(_), UniformType::Vec2) |
+                (Uniform::Vec3(_), UniformType::Vec3) |
+                (Uniform::Vec4(_), UniformType::Vec4) |
+                (Uniform::Mat3(_), UniformType::Mat3) |
+                (Uniform::Mat4(_), UniformType::Mat4)
+        )
+    }
+}
+
+#[allow(dead_code)]
+impl UniformType {
+    pub fn from_glsl(name: &str) -> Option<UniformType> {
+        match name {
+            "int" => Some(UniformType::Int),
+            "bool" => Some(UniformType::Bool),
+            "float" => Some(UniformType::Float),
+            "vec2" => Some(UniformType::Vec2),
+            "vec3" => Some(UniformType::Vec3),
+            "vec4" => Some(UniformType::Vec4),
+            "mat3" => Some(UniformType::Mat3),
+            "mat4" => Some(UniformType::Mat4),
+            "sampler2D" => Some(UniformType::Sampler2D),
+            _ => None,
+        }
+    }
+}
+
+// The names declared with `qualifier` at the top level of a shader, with
+// their type and array length. Handles precision qualifiers, `layout(...)`
+// and several names in one declaration. Array lengths have to be literals.
+fn declarations(
+    source: &str,
+    qualifier: &str,
+) -> Vec<(String, String, Option<usize>)> {
+    let code: String = source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .map(|line| line.split("//").next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut found = vec![];
+    for statement in code.split([';', '{', '}']) {
+        let mut statement = statement.trim();
+        if statement.starts_with("layout") {
+            statement = statement.split_once(')').map_or("", |(_, rest)| rest);
+        }
+
+        let mut tokens = statement.split_whitespace();
+        if tokens.next() != Some(qualifier) {
+            continue;
+        }
+        let Some(glsl_type) = tokens
+            .find(|token| !matches!(*token, "lowp" | "mediump" | "highp"))
+        else {
+            continue;
+        };
+
+        let names = tokens.collect::<Vec<_>>().join(" ");
+        for name in names.split(',') {
+            let (name, length) = match name.split_once('[') {
+                Some((name, length)) => {
+                    (name, length.trim_end_matches([']', ' ']).parse().ok())
+                }
+                None => (name, None),
+            };
+            let name = name.trim();
+            if !name.is_empty() {
+                found.push((name.to_string(), glsl_type.to_string(), length));
+            }
+        }
+    }
+
+    return found;
+}
+
+#[allow(dead_code)]
+impl ProgramInfo {
+    // Read from the declarations in the sources, for backends that can't ask
+    // a driver. Unlike GL's reflection this keeps declarations the compiler
+    // would have optimised out.
+    pub fn from_source(
+        kind: ProgramKind,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> ProgramInfo {
+        let mut info = ProgramInfo {
+            kind,
+            ..ProgramInfo::default()
+        };
+
+        for source in [vertex_source, fragment_source] {
+            for (name, glsl_type, length) in declarations(source, "uniform") {
+                let Some(uniform_type) = UniformType::from_glsl(&glsl_type)
+                else {
+                    continue;
+                };
+                for i in 0..length.unwrap_or(0) {
+                    info.uniforms
+                        .insert(format!("{}[{}]", name, i), uniform_type);
+                }
+                info.uniforms.insert(name, uniform_type);
+            }
+        }
+        for (name, _, _) in declarations(vertex_source, "in") {
+            info.attributes.insert(name);
+        }
+
+        return info;
+    }
+}
@@ -1,185 +1,109 @@
-use web_sys::{
-    WebGl2RenderingContext,
-    WebGlBuffer,
-    WebGlProgram,
-    WebGlUniformLocation,
-};
-
-use crate::colour::Colour;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+use crate::backend::Backend;
+use crate::camera::Camera;
+use crate::error::SpatioError;
+use crate::light::Light;
+use crate::material::Material;
 use crate::matrix::Matrix4F;
+use crate::mesh::Mesh;
+use crate::mesh_object::MeshObject;
+use crate::picking::{MeshHit, Pickable};
 use crate::primitives::Draw;
-use crate::ID_MATRIX;
+use crate::ray::{
+    intersect_box,
+    intersect_transformed,
+    Intersect,
+    Ray,
+    RayHit,
+};
+use crate::transform::Transform;
+use crate::vector::Vec3;
 
+#[allow(dead_code)]
 pub struct Box {
-    position_buffer:             WebGlBuffer,
-    indices_buffer:              WebGlBuffer,
-    position_attribute_location: i32,
-    colour_uniform_location:     WebGlUniformLocation,
-    colour:                      Colour,
-    uniform_matrix_location:     WebGlUniformLocation,
-    program:                     WebGlProgram,
+    object: MeshObject,
+    min:    Vec3,
+    max:    Vec3,
 }
 
+#[allow(dead_code)]
 impl Box {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        context: &WebGl2RenderingContext,
+        backend: &mut dyn Backend,
         top: f32,
         left: f32,
         front: f32,
         bottom: f32,
         right: f32,
         back: f32,
-        colour: Colour,
-        program: WebGlProgram,
-    ) -> Box {
-        //     4------5
-        //    /|     /|
-        //   / |    / |
-        //  0------1  |
-        //  |  7---|--6
-        //  | /    | /
-        //  |/     |/
-        //  3------2
-        let vertices = [
-            left, top, front, // 0
-            right, top, front, // 1
-            right, bottom, front, // 2
-            left, bottom, front, // 3
-            left, top, back, // 4
-            right, top, back, // 5
-            right, bottom, back, // 6
-            left, bottom, back, // 7
-        ];
-
-        //     4------5
-        //    /|     /|
-        //   / |    / |
-        //  0------1  |
-        //  |  7---|--6
-        //  | /    | /
-        //  |/     |/
-        //  3------2
-        //
-        let triangles: [u16; 36] = [
-            0, 1, 2, // FRONT 1
-            0, 2, 3, // FRONT 2
-            4, 5, 1, // TOP 1
-            4, 1, 0, // TOP 2
-            5, 6, 2, // RIGHT 1
-            5, 2, 1, // RIGHT 2
-            6, 7, 2, // BOTTOM 1
-            6, 2, 3, // BOTTOM 2
-            7, 4, 0, // LEFT 1
-            7, 0, 3, // LEFT 2
-            5, 4, 6, // BACK 1
-            5, 6, 7, // BACK 2
-        ];
-
-        let position_buffer = context.create_buffer().unwrap();
-
-        context.bind_buffer(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            Some(&position_buffer),
-        );
-
-        unsafe {
-            let positions_array_buf_view =
-                js_sys::Float32Array::view(&vertices);
-
-            context.buffer_data_with_array_buffer_view(
-                WebGl2RenderingContext::ARRAY_BUFFER,
-                &positions_array_buf_view,
-                WebGl2RenderingContext::STATIC_DRAW,
-            );
-        }
-
-        let indices_buffer = context.create_buffer().unwrap();
-
-        context.bind_buffer(
-            WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
-            Some(&indices_buffer),
-        );
-
-        unsafe {
-            let indices_array_buf_view = js_sys::Uint16Array::view(&triangles);
-
-            context.buffer_data_with_array_buffer_view(
-                WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
-                &indices_array_buf_view,
-                WebGl2RenderingContext::STATIC_DRAW,
-            );
-        }
-
-        let position_attribute_location =
-            context.get_attrib_location(&program, "position");
+        material: Rc<Material>,
+    ) -> Result<Box, SpatioError> {
+        let min = Vec3::new(left.min(right), bottom.min(top), front.min(back));
+        let max = Vec3::new(left.max(right), bottom.max(top), front.max(back));
+
+        let object = MeshObject::new(
+            backend,
+            Mesh::cuboid(min, max),
+            Transform::IDENTITY,
+            material,
+        )?;
+
+        return Ok(Box {
+            object,
+            min,
+            max,
+        });
+    }
+}
 
-        let colour_uniform_location = context
-            .get_uniform_location(&program, "colour")
-            .expect("Missing \"colour\" uniform in program");
+// For the transform, render mode and vertex colours
+impl Deref for Box {
+    type Target = MeshObject;
 
-        let uniform_matrix_location = context
-            .get_uniform_location(&program, "u_matrix")
-            .expect("Missing \"u_matrix\" uniform in program");
+    fn deref(&self) -> &MeshObject {
+        &self.object
+    }
+}
 
-        return Box {
-            position_buffer,
-            indices_buffer,
-            position_attribute_location,
-            colour_uniform_location,
-            uniform_matrix_location,
-            colour,
-            program,
-        };
+impl DerefMut for Box {
+    fn deref_mut(&mut self) -> &mut MeshObject {
+        &mut self.object
     }
 }
 
 impl Draw for Box {
     fn draw(
         &self,
-        context: &web_sys::WebGl2RenderingContext,
-        uniform_matrix: Option<Matrix4F>,
-    ) -> Result<(), String> {
-        context.bind_buffer(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            Some(&self.position_buffer),
-        );
-
-        context.enable_vertex_attrib_array(
-            self.position_attribute_location as u32,
-        );
-
-        context.vertex_attrib_pointer_with_i32(
-            self.position_attribute_location as u32,
-            3,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            0,
-            0,
-        );
-
-        context.bind_buffer(
-            WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
-            Some(&self.indices_buffer),
-        );
-
-        context.use_program(Some(&self.program));
-
-        self.colour.uniform(context, &self.colour_uniform_location);
+        backend: &mut dyn Backend,
+        camera: &Camera,
+        lights: &[Light],
+        parent_matrix: Option<Matrix4F>,
+    ) -> Result<(), SpatioError> {
+        self.object.draw(backend, camera, lights, parent_matrix)
+    }
 
-        context.uniform_matrix4fv_with_f32_array(
-            Some(&self.uniform_matrix_location),
-            false,
-            &uniform_matrix.unwrap_or(ID_MATRIX),
-        );
+    fn is_transparent(&self) -> bool {
+        self.object.is_transparent()
+    }
 
+    fn centre(&self) -> Vec3 {
+        self.object.centre()
+    }
+}
 
-        context.draw_elements_with_i32(
-            WebGl2RenderingContext::TRIANGLES,
-            36,
-            WebGl2RenderingContext::UNSIGNED_SHORT,
-            0,
-        );
+impl Intersect for Box {
+    fn intersect(&self, ray: &Ray) -> Option<RayHit> {
+        intersect_transformed(ray, &self.transform.matrix(), |ray| {
+            intersect_box(ray, self.min, self.max)
+        })
+    }
+}
 
-        return Ok(());
+impl Pickable for Box {
+    fn pick(&self, ray: &Ray) -> Option<MeshHit> {
+        self.object.pick(ray)
     }
 }
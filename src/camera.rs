@@ -0,0 +1,275 @@
+use std::f32::consts::{FRAC_PI_3, PI};
+
+use crate::matrix::{
+    look_at_matrix,
+    mat_mul,
+    orthographic_matrix,
+    perspective_matrix,
+    Mat4,
+    Matrix4F,
+};
+use crate::ray::Ray;
+use crate::vector::{Vec3, Vec4};
+
+// Bounds of the field of view, in radians. At 0 or a half turn the
+// projection degenerates.
+const MIN_FOV: f32 = PI / 180.0;
+const MAX_FOV: f32 = PI * 179.0 / 180.0;
+// Bounds of the near and far planes. A perspective projection needs the near
+// plane in front of the eye, and both need the far plane past the near one.
+const MIN_NEAR: f32 = 1e-4;
+const MAX_FAR: f32 = 1e9;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective,
+    Orthographic,
+}
+
+pub struct Camera {
+    pub eye:          Vec3,
+    pub target:       Vec3,
+    pub up:           Vec3,
+    pub fov:          f32,
+    pub aspect_ratio: f32,
+    pub near:         f32,
+    pub far:          f32,
+    pub projection:   Projection,
+    viewport_width:   f32,
+    viewport_height:  f32,
+    pixel_ratio:      f32,
+}
+
+#[allow(dead_code)]
+impl Camera {
+    pub fn new(eye: Vec3, target: Vec3) -> Camera {
+        Camera {
+            eye,
+            target,
+            up: Vec3::Y,
+            fov: FRAC_PI_3,
+            aspect_ratio: 1.0,
+            near: 0.1,
+            far: 100.0,
+            projection: Projection::Perspective,
+            viewport_width: 1.0,
+            viewport_height: 1.0,
+            pixel_ratio: 1.0,
+        }
+    }
+
+    pub fn view_matrix(&self) -> Matrix4F {
+        look_at_matrix(self.eye, self.target, self.up)
+    }
+
+    // `fov` kept between 1 and 179 degrees
+    pub fn clamped_fov(&self) -> f32 {
+        self.fov.clamp(MIN_FOV, MAX_FOV)
+    }
+
+    // `near` and `far` kept in front of the eye and apart, in that order
+    pub fn clamped_depth_range(&self) -> (f32, f32) {
+        let near = self.near.clamp(MIN_NEAR, MAX_FAR / 2.0);
+        let far = self.far.max(near + near * 1e-3 + MIN_NEAR).min(MAX_FAR);
+        return (near, far);
+    }
+
+    pub fn projection_matrix(&self) -> Matrix4F {
+        let (near, far) = self.clamped_depth_range();
+        match self.projection {
+            Projection::Perspective => perspective_matrix(
+                self.clamped_fov(),
+                self.aspect_ratio,
+                near,
+                far,
+            ),
+            Projection::Orthographic => {
+                let (half_width, half_height) = self.orthographic_extents();
+                orthographic_matrix(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    near,
+                    far,
+                )
+            }
+        }
+    }
+
+    // Size of the orthographic view volume, chosen so that the plane through
+    // the target is framed identically in both projections. This way toggling
+    // between them doesn't make the scene jump.
+    pub fn orthographic_extents(&self) -> (f32, f32) {
+        let half_height = self.distance() * (self.clamped_fov() / 2.0).tan();
+        return (half_height * self.aspect_ratio, half_height);
+    }
+
+    pub fn toggle_projection(&mut self) {
+        self.projection = match self.projection {
+            Projection::Perspective => Projection::Orthographic,
+            Projection::Orthographic => Projection::Perspective,
+        };
+    }
+
+    pub fn view_projection(&self) -> Matrix4F {
+        mat_mul(&self.projection_matrix(), &self.view_matrix())
+    }
+
+    // Size of the drawing buffer in pixels. Also sets the aspect ratio.
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        self.viewport_width = width.max(1) as f32;
+        self.viewport_height = height.max(1) as f32;
+        self.aspect_ratio = self.viewport_width / self.viewport_height;
+    }
+
+    pub fn viewport(&self) -> (f32, f32) {
+        (self.viewport_width, self.viewport_height)
+    }
+
+    // Drawing buffer pixels per CSS pixel, the window's devicePixelRatio
+    pub fn set_pixel_ratio(&mut self, ratio: f32) {
+        if ratio > 0.0 {
+            self.pixel_ratio = ratio;
+        }
+    }
+
+    pub fn pixel_ratio(&self) -> f32 {
+        self.pixel_ratio
+    }
+
+    // Screen coordinates are in drawing buffer pixels from the top left corner
+    // of the canvas. Depth goes from 0.0 on the near plane to 1.0 on the far
+    // plane. Returns None if the view-projection can't be inverted.
+    pub fn unproject(
+        &self,
+        screen_x: f32,
+        screen_y: f32,
+        depth: f32,
+    ) -> Option<Vec3> {
+        let inverse = Mat4(self.view_projection()).inverse()?;
+
+        let ndc = Vec4::new(
+            2.0 * screen_x / self.viewport_width - 1.0,
+            1.0 - 2.0 * screen_y / self.viewport_height,
+            2.0 * depth - 1.0,
+            1.0,
+        );
+
+        return Some((inverse * ndc).perspective_divide());
+    }
+
+    // World space ray going from the near plane through the given pixel
+    pub fn screen_ray(&self, screen_x: f32, screen_y: f32) -> Option<Ray> {
+        let near = self.unproject(screen_x, screen_y, 0.0)?;
+        let far = self.unproject(screen_x, screen_y, 1.0)?;
+        return Some(Ray::new(near, far - near));
+    }
+
+    // Unit vector from the eye towards the target
+    pub fn forward(&self) -> Vec3 {
+        (self.target - self.eye).normalize()
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.eye.distance(self.target)
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera::new(Vec3::new(0.0, 0.0, 3.0), Vec3::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Where a world point lands in normalised device coordinates
+    fn ndc(camera: &Camera, point: Vec3) -> Vec3 {
+        (Mat4(camera.view_projection()) * point.extend(1.0))
+            .perspective_divide()
+    }
+
+    #[test]
+    fn projections_frame_the_target_plane_alike() {
+        let mut camera =
+            Camera::new(Vec3::new(2.0, 3.0, 6.0), Vec3::new(0.0, 1.0, 0.0));
+        camera.set_viewport(800, 400);
+
+        // Points in the plane through the target, facing the camera
+        let right = camera.forward().cross(Vec3::Y).normalize();
+        let up = right.cross(camera.forward());
+        let points = [
+            camera.target,
+            camera.target + right * 1.5,
+            camera.target + up * 0.8 - right * 2.0,
+        ];
+
+        for point in points {
+            let perspective = ndc(&camera, point);
+            camera.toggle_projection();
+            let orthographic = ndc(&camera, point);
+            camera.toggle_projection();
+
+            assert!((perspective.x - orthographic.x).abs() < 1e-5);
+            assert!((perspective.y - orthographic.y).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn fov_is_clamped() {
+        let mut camera = Camera::new(Vec3::new(0.0, 0.0, 3.0), Vec3::ZERO);
+        camera.fov = 0.0;
+        assert!(Mat4(camera.projection_matrix()).inverse().is_some());
+        camera.fov = 4.0;
+        assert!(camera.projection_matrix().iter().all(|x| x.is_finite()));
+        assert!(camera.orthographic_extents().1 > 0.0);
+    }
+
+    #[test]
+    fn depth_range_is_clamped() {
+        let mut camera = Camera::new(Vec3::new(1.0, 2.0, 5.0), Vec3::ZERO);
+        camera.set_viewport(640, 480);
+        let ranges = [
+            (0.0, 100.0),
+            (-1.0, 100.0),
+            (10.0, 10.0),
+            (10.0, 1.0),
+            (0.1, f32::INFINITY),
+            (0.1, f32::NAN),
+        ];
+
+        for (near, far) in ranges {
+            camera.near = near;
+            camera.far = far;
+            let (near, far) = camera.clamped_depth_range();
+            assert!(near > 0.0 && far > near && far.is_finite());
+
+            for projection in
+                [Projection::Perspective, Projection::Orthographic]
+            {
+                camera.projection = projection;
+                assert!(Mat4(camera.projection_matrix()).inverse().is_some());
+                assert!(camera.unproject(320.0, 240.0, 0.5).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn unproject_inverts_the_projection() {
+        let mut camera = Camera::new(Vec3::new(1.0, 2.0, 5.0), Vec3::ZERO);
+        camera.set_viewport(640, 480);
+
+        let point = Vec3::new(0.5, -0.3, 0.2);
+        let ndc = ndc(&camera, point);
+        let screen_x = (ndc.x + 1.0) / 2.0 * 640.0;
+        let screen_y = (1.0 - ndc.y) / 2.0 * 480.0;
+        let back = camera
+            .unproject(screen_x, screen_y, (ndc.z + 1.0) / 2.0)
+            .unwrap();
+
+        assert!(back.distance(point) < 1e-3);
+    }
+}
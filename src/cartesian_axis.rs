@@ -1,80 +1,110 @@
-use web_sys::{WebGl2RenderingContext, WebGlProgram};
+use std::rc::Rc;
 
+use crate::backend::Backend;
 use crate::boxx::Box;
 use crate::colour::Colour;
-use crate::matrix::Matrix4F;
-use crate::primitives::Draw;
+use crate::error::SpatioError;
+use crate::label::Label;
+use crate::material::Material;
+use crate::program::Program;
+use crate::scene::SceneNode;
+use crate::vector::Vec3;
 
+// Drawn by adding `into_node` to a scene, so each box and label is sorted on
+// its own in the transparent pass
+#[allow(dead_code)]
 pub struct CartesianAxis {
-    boxes: [Box; 4],
+    boxes:  [Box; 4],
+    labels: Vec<Label>,
 }
 
-impl<'a> CartesianAxis {
+#[allow(dead_code)]
+impl CartesianAxis {
     pub fn new(
-        context: &WebGl2RenderingContext,
-        program: WebGlProgram,
-    ) -> CartesianAxis {
+        backend: &mut dyn Backend,
+        program: Rc<Program>,
+    ) -> Result<CartesianAxis, SpatioError> {
+        let material = |colour| -> Result<Rc<Material>, SpatioError> {
+            return Ok(Rc::new(Material::new(program.clone(), colour)?));
+        };
+
         let width = 0.01;
         let origin = Box::new(
-            context,
+            backend,
             width,
             -width,
             -width,
             -width,
             width,
             width,
-            Colour::WHITE,
-            program.clone(),
-        );
+            material(Colour::WHITE)?,
+        )?;
         let x_axis = Box::new(
-            context,
+            backend,
             width,
             width,
             -width,
             -width,
             1.0,
             width,
-            Colour::RED,
-            program.clone(),
-        );
+            material(Colour::RED)?,
+        )?;
         let y_axis = Box::new(
-            context,
+            backend,
             1.0,
             -width,
             -width,
             width,
             width,
             width,
-            Colour::GREEN,
-            program.clone(),
-        );
+            material(Colour::GREEN)?,
+        )?;
         let z_axis = Box::new(
-            context,
+            backend,
             width,
             -width,
             -1.0,
             -width,
             width,
             -width,
-            Colour::BLUE,
-            program,
-        );
+            material(Colour::BLUE)?,
+        )?;
 
-        return CartesianAxis {
-            boxes: [origin, x_axis, y_axis, z_axis],
-        };
+        return Ok(CartesianAxis {
+            boxes:  [origin, x_axis, y_axis, z_axis],
+            labels: vec![],
+        });
     }
-}
 
-impl<'a> Draw for CartesianAxis {
-    fn draw(
-        &self,
-        context: &WebGl2RenderingContext,
-        uniform_matrix: Option<Matrix4F>,
-    ) -> Result<(), String> {
-        for b in &self.boxes {
-            b.draw(context, uniform_matrix)?;
+    // Puts the labels just past the ends of the x, y and z axes
+    pub fn with_labels(mut self, labels: [Label; 3]) -> CartesianAxis {
+        let ends = [
+            Vec3::new(1.1, 0.0, 0.0),
+            Vec3::new(0.0, 1.1, 0.0),
+            Vec3::new(0.0, 0.0, -1.1),
+        ];
+
+        self.labels = labels
+            .into_iter()
+            .zip(ends)
+            .map(|(mut label, end)| {
+                label.transform.translation = end;
+                label
+            })
+            .collect();
+        return self;
+    }
+
+    // One child per box and label, so the axes can be moved along with
+    // whatever they are attached to in a scene
+    pub fn into_node(self) -> SceneNode {
+        let mut node = SceneNode::new();
+        for b in self.boxes {
+            node.add_child(SceneNode::with_drawable(Rc::new(b)));
+        }
+        for label in self.labels {
+            node.add_child(SceneNode::with_drawable(Rc::new(label)));
         }
-        return Ok(());
+        return node;
     }
 }
@@ -1,5 +1,4 @@
-use web_sys::{WebGl2RenderingContext, WebGlUniformLocation};
-
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Colour {
     pub r: f32,
     pub g: f32,
@@ -10,6 +9,12 @@ pub struct Colour {
 
 #[allow(dead_code)]
 impl Colour {
+    pub const BLACK: Colour = Colour {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    };
     pub const BLUE: Colour = Colour {
         r: 0.0,
         g: 0.0,
@@ -83,6 +88,21 @@ impl Colour {
         a: 1.0,
     };
 
+    // Linear blend, `self` at 0 and `other` at 1
+    pub fn lerp(self, other: Colour, t: f32) -> Colour {
+        Colour {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+
+    pub fn with_alpha(mut self, a: f32) -> Colour {
+        self.a = a;
+        return self;
+    }
+
     pub fn from_rgb_hex(_hex: &str) -> Colour {
         Colour::PURPLE
     }
@@ -123,17 +143,103 @@ impl Colour {
         }
     }
 
-    pub fn uniform(
-        &self,
-        context: &WebGl2RenderingContext,
-        colour_uniform_location: &WebGlUniformLocation,
-    ) {
-        context.uniform4f(
-            Some(colour_uniform_location),
-            self.r,
-            self.g,
-            self.b,
-            self.a,
+    // Clamped to 0..1 first
+    pub fn to_rgba_u8(self) -> [u8; 4] {
+        let channel =
+            |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        return [
+            channel(self.r),
+            channel(self.g),
+            channel(self.b),
+            channel(self.a),
+        ];
+    }
+}
+
+// Colours placed along 0..1 and blended linearly in between. Before the first
+// stop and after the last the colour is held.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f32, Colour)>,
+}
+
+#[allow(dead_code)]
+impl Gradient {
+    pub fn new(mut stops: Vec<(f32, Colour)>) -> Gradient {
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        return Gradient {
+            stops,
+        };
+    }
+
+    pub fn between(from: Colour, to: Colour) -> Gradient {
+        Gradient::new(vec![(0.0, from), (1.0, to)])
+    }
+
+    pub fn at(&self, t: f32) -> Colour {
+        let Some(&(first_t, first)) = self.stops.first() else {
+            return Colour::WHITE;
+        };
+        if t <= first_t {
+            return first;
+        }
+
+        for pair in self.stops.windows(2) {
+            let [(from_t, from), (to_t, to)] = [pair[0], pair[1]];
+            if t <= to_t {
+                let span = to_t - from_t;
+                let local = if span > 0.0 { (t - from_t) / span } else { 1.0 };
+                return from.lerp(to, local);
+            }
+        }
+
+        return self.stops[self.stops.len() - 1].1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn u8_round_trip() {
+        let colour = Colour::from_rgba_u8(0, 51, 128, 255);
+        assert_eq!(colour.to_rgba_u8(), [0, 51, 128, 255]);
+        assert_eq!(Colour::from_rgb_u8(10, 20, 30).a, 1.0);
+    }
+
+    #[test]
+    fn to_rgba_u8_clamps() {
+        let colour = Colour::from_rgba_f32(-0.5, 1.5, 0.5, 2.0);
+        assert_eq!(colour.to_rgba_u8(), [0, 255, 128, 255]);
+    }
+
+    #[test]
+    fn lerp() {
+        assert_eq!(Colour::BLACK.lerp(Colour::WHITE, 0.0), Colour::BLACK);
+        assert_eq!(Colour::BLACK.lerp(Colour::WHITE, 1.0), Colour::WHITE);
+        assert_eq!(
+            Colour::RED.lerp(Colour::BLUE.with_alpha(0.0), 0.5),
+            Colour::from_rgba_f32(0.5, 0.0, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn gradient_holds_past_its_ends() {
+        let gradient = Gradient::new(vec![
+            (0.8, Colour::WHITE),
+            (0.2, Colour::BLACK),
+            (0.5, Colour::RED),
+        ]);
+
+        assert_eq!(gradient.at(0.0), Colour::BLACK);
+        assert_eq!(gradient.at(0.2), Colour::BLACK);
+        assert_eq!(gradient.at(0.5), Colour::RED);
+        assert_eq!(
+            gradient.at(0.725).to_rgba_u8(),
+            Colour::RED.lerp(Colour::WHITE, 0.75).to_rgba_u8()
         );
+        assert_eq!(gradient.at(1.0), Colour::WHITE);
+        assert_eq!(Gradient::new(vec![]).at(0.5), Colour::WHITE);
     }
 }
@@ -0,0 +1,80 @@
+use std::error::Error;
+use std::fmt;
+
+#[cfg(feature = "web")]
+use wasm_bindgen::JsValue;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpatioError {
+    // The browser gave us neither a WebGL2 nor a WebGL1 context
+    ContextCreation(String),
+    // Carry the driver's info log
+    ShaderCompile(String),
+    ProgramLink(String),
+    // A program used by a primitive doesn't declare a uniform it sets
+    MissingUniform(String),
+    // A uniform set with a value of another type than it's declared with
+    WrongUniformType(String),
+    BufferCreation,
+    // Geometry that can't be built, e.g. too many vertices for 16 bit indices
+    InvalidGeometry(String),
+    TextureCreation(String),
+    // Reading or writing an image file failed
+    Image(String),
+    // Anything else the browser threw at us, e.g. a missing element
+    Dom(String),
+}
+
+impl fmt::Display for SpatioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpatioError::ContextCreation(reason) => {
+                write!(f, "Unable to create a WebGL context: {}", reason)
+            }
+            SpatioError::ShaderCompile(log) => {
+                write!(f, "Unable to compile shader: {}", log)
+            }
+            SpatioError::ProgramLink(log) => {
+                write!(f, "Unable to link program: {}", log)
+            }
+            SpatioError::MissingUniform(name) => {
+                write!(f, "Missing \"{}\" uniform in program", name)
+            }
+            SpatioError::WrongUniformType(name) => {
+                write!(f, "Wrong type of value for \"{}\" uniform", name)
+            }
+            SpatioError::BufferCreation => {
+                write!(f, "Unable to create buffer")
+            }
+            SpatioError::InvalidGeometry(reason) => {
+                write!(f, "Invalid geometry: {}", reason)
+            }
+            SpatioError::TextureCreation(reason) => {
+                write!(f, "Unable to create texture: {}", reason)
+            }
+            SpatioError::Image(reason) => {
+                write!(f, "Unable to read or write image: {}", reason)
+            }
+            SpatioError::Dom(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for SpatioError {}
+
+#[cfg(feature = "web")]
+impl From<JsValue> for SpatioError {
+    fn from(value: JsValue) -> SpatioError {
+        SpatioError::Dom(
+            value.as_string().unwrap_or_else(|| format!("{:?}", value)),
+        )
+    }
+}
+
+// Thrown to JS as an `Error` carrying the message
+#[cfg(feature = "web")]
+impl From<SpatioError> for JsValue {
+    fn from(error: SpatioError) -> JsValue {
+        js_sys::Error::new(&error.to_string()).into()
+    }
+}
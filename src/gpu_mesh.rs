@@ -0,0 +1,277 @@
+use crate::backend::{Backend, BufferData, BufferHandle, DrawMode, Uniform};
+use crate::error::SpatioError;
+use crate::mesh::Mesh;
+use crate::program::Program;
+
+// A `Mesh` uploaded to a backend's buffers. The CPU copy isn't kept, hold on
+// to the `Mesh` if it's still needed (e.g. for picking).
+pub struct GpuMesh {
+    position_buffer: BufferHandle,
+    normal_buffer:   Option<BufferHandle>,
+    uv_buffer:       Option<BufferHandle>,
+    colour_buffer:   Option<BufferHandle>,
+    indices_buffer:  BufferHandle,
+    indices_count:   usize,
+    // Pairs of indices for drawing lines, only uploaded when asked for
+    edges_buffer:    Option<BufferHandle>,
+    edges_count:     usize,
+}
+
+fn upload_optional(
+    backend: &mut dyn Backend,
+    data: &[f32],
+) -> Result<Option<BufferHandle>, SpatioError> {
+    if data.is_empty() {
+        return Ok(None);
+    }
+    return Ok(Some(backend.create_buffer(BufferData::Vertices(data))?));
+}
+
+#[allow(dead_code)]
+impl GpuMesh {
+    pub fn new(
+        backend: &mut dyn Backend,
+        mesh: &Mesh,
+    ) -> Result<GpuMesh, SpatioError> {
+        let mut created = vec![];
+        let gpu_mesh = GpuMesh::upload(backend, mesh, &mut created);
+        if gpu_mesh.is_err() {
+            // Doesn't leak what was uploaded before the failure
+            for buffer in created {
+                backend.delete_buffer(buffer);
+            }
+        }
+        return gpu_mesh;
+    }
+
+    // Keeps the buffers in `created` as they're uploaded
+    fn upload(
+        backend: &mut dyn Backend,
+        mesh: &Mesh,
+        created: &mut Vec<BufferHandle>,
+    ) -> Result<GpuMesh, SpatioError> {
+        let position_buffer =
+            backend.create_buffer(BufferData::Vertices(&mesh.positions))?;
+        created.push(position_buffer);
+        let normal_buffer = upload_optional(backend, &mesh.normals)?;
+        created.extend(normal_buffer);
+        let uv_buffer = upload_optional(backend, &mesh.uvs)?;
+        created.extend(uv_buffer);
+        let colour_buffer = upload_optional(backend, &mesh.colours)?;
+        created.extend(colour_buffer);
+        let indices_buffer =
+            backend.create_buffer(BufferData::Indices(&mesh.indices))?;
+
+        return Ok(GpuMesh {
+            position_buffer,
+            normal_buffer,
+            uv_buffer,
+            colour_buffer,
+            indices_buffer,
+            indices_count: mesh.indices.len(),
+            edges_buffer: None,
+            edges_count: 0,
+        });
+    }
+
+    // Replaces the edges `draw_edges` draws, e.g. with `Mesh::edges`
+    pub fn set_edges(
+        &mut self,
+        backend: &mut dyn Backend,
+        edges: &[[u16; 2]],
+    ) -> Result<(), SpatioError> {
+        let indices: Vec<u16> = edges.iter().flatten().copied().collect();
+        let buffer = backend.create_buffer(BufferData::Indices(&indices))?;
+
+        if let Some(old) = self.edges_buffer.replace(buffer) {
+            backend.delete_buffer(old);
+        }
+        self.edges_count = indices.len();
+
+        return Ok(());
+    }
+
+    // Points `program`'s `position`, `normal`, `uv` and `vertex_colour`
+    // attributes at the buffers, skipping those it doesn't declare.
+    // Attributes the mesh doesn't have are disabled so the shader reads a
+    // constant instead, and `u_vertex_colours` tells the shader whether there
+    // are colours to read.
+    pub fn bind(
+        &self,
+        backend: &mut dyn Backend,
+        program: &Program,
+    ) -> Result<(), SpatioError> {
+        let attributes = [
+            ("position", Some(self.position_buffer), 3),
+            ("normal", self.normal_buffer, 3),
+            ("uv", self.uv_buffer, 2),
+            ("vertex_colour", self.colour_buffer, 4),
+        ];
+
+        for (name, buffer, size) in attributes {
+            if !program.has_attribute(name) {
+                continue;
+            }
+            match buffer {
+                Some(buffer) => backend.bind_attribute(name, buffer, size),
+                None => backend.disable_attribute(name),
+            }
+        }
+
+        if program.has_uniform("u_vertex_colours") {
+            let enabled = self.colour_buffer.is_some();
+            program.set_uniform(
+                backend,
+                "u_vertex_colours",
+                Uniform::Int(enabled as i32),
+            )?;
+        }
+
+        return Ok(());
+    }
+
+    // Replaces the per-vertex colours, or removes them when `colours` is
+    // empty
+    pub fn set_colours(
+        &mut self,
+        backend: &mut dyn Backend,
+        colours: &[f32],
+    ) -> Result<(), SpatioError> {
+        let buffer = upload_optional(backend, colours)?;
+
+        if let Some(old) = std::mem::replace(&mut self.colour_buffer, buffer) {
+            backend.delete_buffer(old);
+        }
+
+        return Ok(());
+    }
+
+    // Expects `bind` to have been called with the current program
+    pub fn draw(&self, backend: &mut dyn Backend) {
+        backend.draw(
+            DrawMode::Triangles,
+            Some(self.indices_buffer),
+            self.indices_count,
+        );
+    }
+
+    // Draws nothing until `set_edges` has been called
+    pub fn draw_edges(&self, backend: &mut dyn Backend) {
+        if let Some(buffer) = self.edges_buffer {
+            backend.draw(DrawMode::Lines, Some(buffer), self.edges_count);
+        }
+    }
+
+    pub fn delete(self, backend: &mut dyn Backend) {
+        let buffers = [
+            Some(self.position_buffer),
+            self.normal_buffer,
+            self.uv_buffer,
+            self.colour_buffer,
+            Some(self.indices_buffer),
+            self.edges_buffer,
+        ];
+        for buffer in buffers.into_iter().flatten() {
+            backend.delete_buffer(buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colour::Colour;
+    use crate::recording::{Command, RecordingBackend};
+    use crate::vector::Vec3;
+
+    fn bound_attributes(backend: &RecordingBackend) -> Vec<(&str, bool)> {
+        let mut attributes = vec![];
+        for command in &backend.commands {
+            match command {
+                Command::BindAttribute {
+                    name, ..
+                } => attributes.push((name.as_str(), true)),
+                Command::DisableAttribute(name) => {
+                    attributes.push((name.as_str(), false))
+                }
+                _ => {}
+            }
+        }
+        return attributes;
+    }
+
+    #[test]
+    fn bind_disables_missing_attributes() {
+        let mut backend = RecordingBackend::new();
+        let program = Program::standard(&mut backend).unwrap();
+
+        // Positions and normals only
+        let mut mesh = Mesh::triangle(Vec3::ZERO, Vec3::X, Vec3::Y);
+        mesh.uvs.clear();
+        let gpu_mesh = GpuMesh::new(&mut backend, &mesh).unwrap();
+        gpu_mesh.bind(&mut backend, &program).unwrap();
+        assert_eq!(
+            bound_attributes(&backend),
+            vec![
+                ("position", true),
+                ("normal", true),
+                ("uv", false),
+                ("vertex_colour", false),
+            ]
+        );
+        assert_eq!(
+            backend.commands.last(),
+            Some(&Command::SetUniform {
+                name:  String::from("u_vertex_colours"),
+                value: Uniform::Int(0),
+            })
+        );
+
+        // The unlit program has no normals to bind
+        let unlit = Program::unlit(&mut backend).unwrap();
+        let mesh = mesh.with_colour(&Colour::RED);
+        let gpu_mesh = GpuMesh::new(&mut backend, &mesh).unwrap();
+        backend.commands.clear();
+        gpu_mesh.bind(&mut backend, &unlit).unwrap();
+        assert_eq!(
+            bound_attributes(&backend),
+            vec![("position", true), ("uv", false), ("vertex_colour", true)]
+        );
+    }
+
+    #[test]
+    fn failed_uploads_dont_leak() {
+        let mesh = Mesh::triangle(Vec3::ZERO, Vec3::X, Vec3::Y);
+
+        // Position, normal and UV buffers, then the indices fail
+        let mut backend = RecordingBackend::new();
+        backend.buffer_limit = Some(3);
+        let gpu_mesh = GpuMesh::new(&mut backend, &mesh);
+        assert_eq!(gpu_mesh.err(), Some(SpatioError::BufferCreation));
+        assert!(backend.live_buffers().is_empty());
+
+        // The old edges are kept when the new ones fail
+        backend.buffer_limit = None;
+        let mut gpu_mesh = GpuMesh::new(&mut backend, &mesh).unwrap();
+        gpu_mesh.set_edges(&mut backend, &[[0, 1]]).unwrap();
+        let live = backend.live_buffers();
+
+        backend.buffer_limit = Some(0);
+        assert!(gpu_mesh.set_edges(&mut backend, &[[1, 2], [2, 0]]).is_err());
+        assert!(gpu_mesh.set_colours(&mut backend, &[1.0; 12]).is_err());
+        assert_eq!(backend.live_buffers(), live);
+        assert_eq!(gpu_mesh.edges_count, 2);
+
+        gpu_mesh.draw_edges(&mut backend);
+        assert!(matches!(
+            backend.commands.last(),
+            Some(Command::Draw {
+                count: 2,
+                ..
+            })
+        ));
+
+        gpu_mesh.delete(&mut backend);
+        assert!(backend.live_buffers().is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(feature = "png")]
+use std::fs::File;
+#[cfg(feature = "png")]
+use std::io::{BufReader, BufWriter, Read, Write};
+#[cfg(feature = "png")]
+use std::path::Path;
+
+#[cfg(feature = "png")]
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+
+use crate::colour::Colour;
+#[cfg(feature = "png")]
+use crate::error::SpatioError;
+
+// 8 bit RGBA pixels, rows from top to bottom
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width:  u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+// Keeps the message of an I/O or PNG error
+#[cfg(feature = "png")]
+fn image_error(error: impl ToString) -> SpatioError {
+    SpatioError::Image(error.to_string())
+}
+
+#[allow(dead_code)]
+impl Image {
+    // Transparent black
+    pub fn new(width: u32, height: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    // A `size` pixels square texture of `cells` by `cells` squares outlined
+    // in `line`, e.g. for showing how a surface is mapped
+    pub fn grid(
+        size: u32,
+        cells: u32,
+        line: Colour,
+        background: Colour,
+    ) -> Image {
+        let mut image = Image::new(size, size);
+        let cell = (size / cells.max(1)).max(1);
+        let (line, background) = (line.to_rgba_u8(), background.to_rgba_u8());
+
+        for y in 0..size {
+            for x in 0..size {
+                // Lines on both sides of the texture so they meet at seams
+                let on_line = |i: u32| i.is_multiple_of(cell) || i == size - 1;
+                let pixel = if on_line(x) || on_line(y) {
+                    line
+                } else {
+                    background
+                };
+                image.set(x, y, pixel);
+            }
+        }
+
+        return image;
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        (y as usize * self.width as usize + x as usize) * 4
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = self.offset(x, y);
+        return [
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ];
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, pixel: [u8; 4]) {
+        let i = self.offset(x, y);
+        self.pixels[i..i + 4].copy_from_slice(&pixel);
+    }
+
+    // Largest difference between any two channels of the images, None if
+    // their sizes differ. Lets image comparisons allow for rounding.
+    pub fn max_difference(&self, other: &Image) -> Option<u8> {
+        if self.width != other.width || self.height != other.height {
+            return None;
+        }
+
+        return Some(
+            self.pixels
+                .iter()
+                .zip(&other.pixels)
+                .map(|(a, b)| a.abs_diff(*b))
+                .max()
+                .unwrap_or(0),
+        );
+    }
+}
+
+// Files are only for native builds, the browser has its own image decoding
+#[cfg(feature = "png")]
+#[allow(dead_code)]
+impl Image {
+    pub fn write_png(&self, writer: impl Write) -> Result<(), SpatioError> {
+        let mut encoder = Encoder::new(writer, self.width, self.height);
+        encoder.set_color(ColorType::Rgba);
+        encoder.set_depth(BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(image_error)?;
+        return writer.write_image_data(&self.pixels).map_err(image_error);
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), SpatioError> {
+        let file = File::create(path).map_err(image_error)?;
+        return self.write_png(BufWriter::new(file));
+    }
+
+    // Palette, grey and RGB images are expanded to RGBA
+    pub fn read_png(reader: impl Read) -> Result<Image, SpatioError> {
+        let mut decoder = Decoder::new(reader);
+        decoder.set_transformations(
+            Transformations::EXPAND | Transformations::STRIP_16,
+        );
+
+        let mut reader = decoder.read_info().map_err(image_error)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(image_error)?;
+        buffer.truncate(info.buffer_size());
+
+        let pixels = match info.color_type {
+            ColorType::Rgba => buffer,
+            ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            ColorType::Grayscale => {
+                buffer.iter().flat_map(|&p| [p, p, p, 255]).collect()
+            }
+            ColorType::Indexed => {
+                return Err(SpatioError::Image(String::from(
+                    "Unexpanded palette PNG",
+                )));
+            }
+        };
+
+        return Ok(Image {
+            width: info.width,
+            height: info.height,
+            pixels,
+        });
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Image, SpatioError> {
+        let file = File::open(path).map_err(image_error)?;
+        return Image::read_png(BufReader::new(file));
+    }
+}
+
+#[cfg(all(test, feature = "png"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_round_trip() {
+        let mut image = Image::new(3, 2);
+        image.set(0, 0, [255, 0, 0, 255]);
+        image.set(2, 1, [10, 20, 30, 40]);
+
+        let mut png = vec![];
+        image.write_png(&mut png).unwrap();
+        assert_eq!(Image::read_png(png.as_slice()).unwrap(), image);
+
+        assert!(Image::read_png(&png[..png.len() / 2]).is_err());
+    }
+}
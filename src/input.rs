@@ -0,0 +1,253 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::{window, HtmlCanvasElement, MouseEvent, TouchEvent, WheelEvent};
+
+use crate::camera::Camera;
+use crate::error::SpatioError;
+use crate::orbit::OrbitControls;
+use crate::utils;
+
+#[derive(Clone, Copy, PartialEq)]
+enum DragMode {
+    Rotate,
+    Pan,
+}
+
+struct Drag {
+    mode:  DragMode,
+    x:     i32,
+    y:     i32,
+    moved: bool,
+}
+
+// Left drag rotates, right drag or shift + left drag pans, wheel zooms.
+// Returns a flag telling whether the last mouse press turned into a drag,
+// so click handlers can ignore the click that ends a drag.
+pub fn attach_mouse_controls(
+    canvas: &HtmlCanvasElement,
+    controls: Rc<RefCell<OrbitControls>>,
+    camera: Rc<RefCell<Camera>>,
+) -> Result<Rc<RefCell<bool>>, SpatioError> {
+    let window =
+        window().ok_or_else(|| SpatioError::Dom(String::from("No window")))?;
+    let drag: Rc<RefCell<Option<Drag>>> = Rc::new(RefCell::new(None));
+    let dragged = Rc::new(RefCell::new(false));
+
+    {
+        let drag = drag.clone();
+        let dragged = dragged.clone();
+
+        utils::create_mouse_handler(
+            canvas,
+            "mousedown",
+            Box::new(move |event: MouseEvent| {
+                let mode = match event.button() {
+                    0 if event.shift_key() => DragMode::Pan,
+                    0 => DragMode::Rotate,
+                    2 => DragMode::Pan,
+                    _ => return,
+                };
+
+                event.prevent_default();
+                *dragged.borrow_mut() = false;
+                *drag.borrow_mut() = Some(Drag {
+                    mode,
+                    x: event.client_x(),
+                    y: event.client_y(),
+                    moved: false,
+                });
+            }),
+        )?;
+    }
+
+    // Move and release are listened to on the window so a drag keeps going
+    // when the cursor leaves the canvas
+    {
+        let drag = drag.clone();
+        let controls = controls.clone();
+
+        let js_closure =
+            Closure::<dyn FnMut(MouseEvent)>::new(move |event: MouseEvent| {
+                let mut drag = drag.borrow_mut();
+                let Some(drag) = drag.as_mut() else {
+                    return;
+                };
+
+                let dx = (event.client_x() - drag.x) as f32;
+                let dy = (event.client_y() - drag.y) as f32;
+                drag.x = event.client_x();
+                drag.y = event.client_y();
+                drag.moved |= dx != 0.0 || dy != 0.0;
+
+                let mut controls = controls.borrow_mut();
+                match drag.mode {
+                    DragMode::Rotate => controls.rotate(dx, dy),
+                    DragMode::Pan => {
+                        // Panning works in drawing buffer pixels
+                        let camera = camera.borrow();
+                        let ratio = camera.pixel_ratio();
+                        controls.pan(dx * ratio, dy * ratio, &camera);
+                    }
+                }
+            });
+        window.add_event_listener_with_callback(
+            "mousemove",
+            js_closure.as_ref().unchecked_ref(),
+        )?;
+        js_closure.forget();
+    }
+
+    {
+        let drag = drag.clone();
+        let dragged = dragged.clone();
+
+        let js_closure =
+            Closure::<dyn FnMut(MouseEvent)>::new(move |_: MouseEvent| {
+                if let Some(drag) = drag.borrow_mut().take() {
+                    *dragged.borrow_mut() = drag.moved;
+                }
+            });
+        window.add_event_listener_with_callback(
+            "mouseup",
+            js_closure.as_ref().unchecked_ref(),
+        )?;
+        js_closure.forget();
+    }
+
+    utils::create_mouse_handler(
+        canvas,
+        "contextmenu",
+        Box::new(|event: MouseEvent| event.prevent_default()),
+    )?;
+
+    {
+        let js_closure =
+            Closure::<dyn FnMut(WheelEvent)>::new(move |event: WheelEvent| {
+                event.prevent_default();
+
+                // Normalise line and page scrolling to roughly pixels
+                let scale = match event.delta_mode() {
+                    WheelEvent::DOM_DELTA_LINE => 16.0,
+                    WheelEvent::DOM_DELTA_PAGE => 400.0,
+                    _ => 1.0,
+                };
+                controls.borrow_mut().zoom((event.delta_y() * scale) as f32);
+            });
+        canvas.add_event_listener_with_callback(
+            "wheel",
+            js_closure.as_ref().unchecked_ref(),
+        )?;
+        js_closure.forget();
+    }
+
+    return Ok(dragged);
+}
+
+struct TouchPoint {
+    id: i32,
+    x:  f32,
+    y:  f32,
+}
+
+fn touch_points(event: &TouchEvent) -> Vec<TouchPoint> {
+    let touches = event.touches();
+    (0..touches.length())
+        .filter_map(|i| touches.get(i))
+        .map(|touch| TouchPoint {
+            id: touch.identifier(),
+            x:  touch.client_x() as f32,
+            y:  touch.client_y() as f32,
+        })
+        .collect()
+}
+
+// Centre and spread of the first two touches
+fn pinch(a: &TouchPoint, b: &TouchPoint) -> (f32, f32, f32) {
+    let distance = ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt();
+    return ((a.x + b.x) / 2.0, (a.y + b.y) / 2.0, distance);
+}
+
+// One finger rotates, two fingers pinch to zoom and drag to pan. The canvas
+// gets `touch-action: none` so the browser doesn't scroll or zoom the page
+// underneath; taps are left alone so they still produce clicks for picking.
+pub fn attach_touch_controls(
+    canvas: &HtmlCanvasElement,
+    controls: Rc<RefCell<OrbitControls>>,
+    camera: Rc<RefCell<Camera>>,
+) -> Result<(), SpatioError> {
+    canvas.style().set_property("touch-action", "none")?;
+
+    let previous: Rc<RefCell<Vec<TouchPoint>>> = Rc::new(RefCell::new(vec![]));
+
+    // Any change in the number of fingers restarts the gesture from the
+    // current positions so the camera doesn't jump
+    let reset = {
+        let previous = previous.clone();
+        move |event: TouchEvent| {
+            *previous.borrow_mut() = touch_points(&event);
+        }
+    };
+
+    for event_type in ["touchstart", "touchend", "touchcancel"] {
+        let js_closure = Closure::<dyn FnMut(TouchEvent)>::new(reset.clone());
+        canvas.add_event_listener_with_callback(
+            event_type,
+            js_closure.as_ref().unchecked_ref(),
+        )?;
+        js_closure.forget();
+    }
+
+    let js_closure =
+        Closure::<dyn FnMut(TouchEvent)>::new(move |event: TouchEvent| {
+            event.prevent_default();
+
+            let current = touch_points(&event);
+            let mut previous = previous.borrow_mut();
+
+            let same_fingers = current.len() == previous.len() &&
+                current
+                    .iter()
+                    .zip(previous.iter())
+                    .all(|(a, b)| a.id == b.id);
+
+            if same_fingers {
+                let mut controls = controls.borrow_mut();
+
+                match (current.as_slice(), previous.as_slice()) {
+                    ([now], [before]) => {
+                        controls.rotate(now.x - before.x, now.y - before.y);
+                    }
+                    ([a, b, ..], [before_a, before_b, ..]) => {
+                        let (x, y, spread) = pinch(a, b);
+                        let (before_x, before_y, before_spread) =
+                            pinch(before_a, before_b);
+
+                        if spread > 0.0 {
+                            controls.zoom_by(before_spread / spread);
+                        }
+
+                        let camera = camera.borrow();
+                        let ratio = camera.pixel_ratio();
+                        controls.pan(
+                            (x - before_x) * ratio,
+                            (y - before_y) * ratio,
+                            &camera,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+
+            *previous = current;
+        });
+    canvas.add_event_listener_with_callback(
+        "touchmove",
+        js_closure.as_ref().unchecked_ref(),
+    )?;
+    js_closure.forget();
+
+    return Ok(());
+}
@@ -0,0 +1,366 @@
+use std::rc::Rc;
+
+#[cfg(feature = "web")]
+use wasm_bindgen::JsCast;
+#[cfg(feature = "web")]
+use web_sys::{window, CanvasRenderingContext2d, HtmlCanvasElement};
+
+use crate::backend::{Backend, BufferData, BufferHandle, DrawMode};
+use crate::camera::Camera;
+#[cfg(feature = "web")]
+use crate::colour::Colour;
+use crate::error::SpatioError;
+use crate::light::Light;
+use crate::material::Material;
+use crate::matrix::Matrix4F;
+use crate::primitives::Draw;
+#[cfg(feature = "web")]
+use crate::program::Program;
+#[cfg(feature = "web")]
+use crate::texture::Texture;
+use crate::transform::Transform;
+use crate::vector::{Vec3, Vertex};
+
+// How label text is drawn. The size is in CSS pixels, like in a stylesheet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Font {
+    pub family: String,
+    pub size:   f32,
+    pub bold:   bool,
+    pub italic: bool,
+}
+
+#[allow(dead_code)]
+impl Font {
+    pub fn new(family: &str, size: f32) -> Font {
+        Font {
+            family: family.to_string(),
+            size,
+            bold: false,
+            italic: false,
+        }
+    }
+
+    pub fn bold(mut self) -> Font {
+        self.bold = true;
+        return self;
+    }
+
+    pub fn italic(mut self) -> Font {
+        self.italic = true;
+        return self;
+    }
+
+    // CSS `font` shorthand at `scale` times the size, e.g.
+    // `italic bold 16px sans-serif`
+    pub fn css(&self, scale: f32) -> String {
+        let mut css = String::new();
+        if self.italic {
+            css.push_str("italic ");
+        }
+        if self.bold {
+            css.push_str("bold ");
+        }
+        css.push_str(&format!("{}px {}", self.size * scale, self.family));
+        return css;
+    }
+}
+
+impl Default for Font {
+    fn default() -> Font {
+        Font::new("sans-serif", 16.0)
+    }
+}
+
+// Text or an image pinned to a point in the scene. It's drawn with a
+// billboard program (see `Program::billboard`) as a quad that always faces
+// the camera and keeps its size in pixels, so it stays readable however far
+// away the point is.
+pub struct Label {
+    // Only the translation is used, it's the point the label is pinned to
+    pub transform:   Transform,
+    // When true the label is hidden by whatever is in front of its point,
+    // otherwise it's drawn over everything
+    pub occludable:  bool,
+    position_buffer: BufferHandle,
+    uv_buffer:       BufferHandle,
+    width:           f32,
+    height:          f32,
+    material:        Rc<Material>,
+}
+
+// Two triangles, in pixels from the label's point
+fn quad(width: f32, height: f32, offset: [f32; 2]) -> [f32; 12] {
+    let (left, right) = (offset[0] - width / 2.0, offset[0] + width / 2.0);
+    let (bottom, top) = (offset[1] - height / 2.0, offset[1] + height / 2.0);
+
+    return [
+        left, bottom, right, bottom, right, top, left, bottom, right, top,
+        left, top,
+    ];
+}
+
+// Draws `text` in white on a transparent canvas sized to fit it, so the
+// material's colour tints it. Sized in device pixels, to stay sharp on HiDPI
+// screens.
+#[cfg(feature = "web")]
+fn draw_text(
+    text: &str,
+    font: &Font,
+) -> Result<HtmlCanvasElement, SpatioError> {
+    let window =
+        window().ok_or_else(|| SpatioError::Dom(String::from("No window")))?;
+    let document = window
+        .document()
+        .ok_or_else(|| SpatioError::Dom(String::from("No document")))?;
+
+    let canvas: HtmlCanvasElement = document
+        .create_element("canvas")?
+        .dyn_into()
+        .map_err(|_| SpatioError::Dom(String::from("Not a canvas")))?;
+    let context: CanvasRenderingContext2d = canvas
+        .get_context("2d")?
+        .and_then(|context| context.dyn_into().ok())
+        .ok_or_else(|| {
+            SpatioError::Dom(String::from("2D canvas isn't supported"))
+        })?;
+
+    let scale = window.device_pixel_ratio() as f32;
+    let css = font.css(scale);
+    context.set_font(&css);
+    let width = context.measure_text(text)?.width() as f32;
+
+    // Room for the antialiasing, and for what goes past the em box
+    let padding = (font.size * scale / 4.0).ceil();
+    canvas.set_width((width + padding * 2.0).ceil() as u32);
+    canvas.set_height((font.size * scale + padding * 2.0).ceil() as u32);
+
+    // Resizing the canvas resets the context
+    context.set_font(&css);
+    context.set_fill_style_str("white");
+    context.set_text_align("center");
+    context.set_text_baseline("middle");
+    context.fill_text(
+        text,
+        canvas.width() as f64 / 2.0,
+        canvas.height() as f64 / 2.0,
+    )?;
+
+    return Ok(canvas);
+}
+
+#[allow(dead_code)]
+impl Label {
+    // A `width` by `height` pixels quad centred on `position`, showing the
+    // material's texture. Fails when the material's program isn't a
+    // billboard one.
+    pub fn new(
+        backend: &mut dyn Backend,
+        position: Vertex,
+        width: f32,
+        height: f32,
+        material: Rc<Material>,
+    ) -> Result<Label, SpatioError> {
+        material.program().require_uniforms(&["u_viewport"])?;
+
+        let uvs = [0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0];
+        let position_buffer = backend.create_buffer(BufferData::Vertices(
+            &quad(width, height, [0.0, 0.0]),
+        ))?;
+        let uv_buffer = match backend.create_buffer(BufferData::Vertices(&uvs))
+        {
+            Ok(buffer) => buffer,
+            Err(error) => {
+                backend.delete_buffer(position_buffer);
+                return Err(error);
+            }
+        };
+
+        return Ok(Label {
+            transform: Transform::from_translation(Vec3::from(position)),
+            occludable: false,
+            position_buffer,
+            uv_buffer,
+            width,
+            height,
+            material,
+        });
+    }
+
+    // `text` in `font` and `colour`, centred on `position`
+    #[cfg(feature = "web")]
+    pub fn text(
+        backend: &mut dyn Backend,
+        program: Rc<Program>,
+        position: Vertex,
+        text: &str,
+        font: &Font,
+        colour: Colour,
+    ) -> Result<Label, SpatioError> {
+        let canvas = draw_text(text, font)?;
+        let texture = Rc::new(Texture::from_canvas(backend, &canvas)?);
+        let label = Material::new(program, colour)
+            .and_then(|material| material.with_texture(texture.clone()))
+            .and_then(|material| {
+                Label::new(
+                    backend,
+                    position,
+                    canvas.width() as f32,
+                    canvas.height() as f32,
+                    Rc::new(material),
+                )
+            });
+
+        // On failure the material went with its reference to the texture
+        if label.is_err() {
+            if let Ok(texture) = Rc::try_unwrap(texture) {
+                texture.delete(backend);
+            }
+        }
+        return label;
+    }
+
+    pub fn with_occlusion(mut self, occludable: bool) -> Label {
+        self.occludable = occludable;
+        return self;
+    }
+
+    // In pixels
+    pub fn size(&self) -> (f32, f32) {
+        (self.width, self.height)
+    }
+
+    // Moves the quad `offset` pixels away from the label's point, x right
+    // and y up, e.g. to put the text beside a marker rather than over it
+    pub fn set_offset(
+        &mut self,
+        backend: &mut dyn Backend,
+        offset: [f32; 2],
+    ) -> Result<(), SpatioError> {
+        let position_buffer = backend.create_buffer(BufferData::Vertices(
+            &quad(self.width, self.height, offset),
+        ))?;
+        backend.delete_buffer(self.position_buffer);
+        self.position_buffer = position_buffer;
+
+        return Ok(());
+    }
+
+    // Frees the quad, and the material's texture when nothing else shares
+    // the material or the texture, like the ones `text` makes
+    pub fn delete(self, backend: &mut dyn Backend) {
+        backend.delete_buffer(self.position_buffer);
+        backend.delete_buffer(self.uv_buffer);
+
+        let texture = Rc::try_unwrap(self.material)
+            .ok()
+            .and_then(|material| material.texture)
+            .and_then(|texture| Rc::try_unwrap(texture).ok());
+        if let Some(texture) = texture {
+            texture.delete(backend);
+        }
+    }
+}
+
+impl Draw for Label {
+    fn draw(
+        &self,
+        backend: &mut dyn Backend,
+        camera: &Camera,
+        lights: &[Light],
+        parent_matrix: Option<Matrix4F>,
+    ) -> Result<(), SpatioError> {
+        let program = self.material.program();
+
+        self.material.apply(backend, lights)?;
+        backend.bind_attribute("position", self.position_buffer, 2);
+        backend.bind_attribute("uv", self.uv_buffer, 2);
+        program.set_matrices(
+            backend,
+            camera,
+            parent_matrix,
+            &Transform::from_translation(self.transform.translation),
+        )?;
+
+        backend.set_depth_test(self.occludable);
+        backend.draw(DrawMode::Triangles, None, 6);
+        backend.set_depth_test(true);
+
+        return Ok(());
+    }
+
+    fn is_transparent(&self) -> bool {
+        self.material.is_transparent()
+    }
+
+    fn centre(&self) -> Vec3 {
+        self.transform.translation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program::Program;
+    use crate::recording::{Command, RecordingBackend};
+    use crate::texture::Texture;
+
+    fn textured(backend: &mut RecordingBackend) -> Material {
+        let program = Rc::new(Program::billboard(backend).unwrap());
+        let texture = Texture::from_rgba(backend, 1, 1, &[255; 4]).unwrap();
+        return Material::textured(program, Rc::new(texture)).unwrap();
+    }
+
+    fn deleted_textures(backend: &RecordingBackend) -> usize {
+        backend
+            .commands
+            .iter()
+            .filter(|command| matches!(command, Command::DeleteTexture(_)))
+            .count()
+    }
+
+    #[test]
+    fn failed_creation_doesnt_leak() {
+        let mut backend = RecordingBackend::new();
+        let material = Rc::new(textured(&mut backend));
+
+        backend.buffer_limit = Some(1);
+        let label = Label::new(
+            &mut backend,
+            Vertex::new(0.0, 0.0, 0.0),
+            4.0,
+            2.0,
+            material,
+        );
+        assert!(label.is_err());
+        assert!(backend.live_buffers().is_empty());
+    }
+
+    #[test]
+    fn delete_frees_unshared_textures() {
+        let mut backend = RecordingBackend::new();
+        let origin = Vertex::new(0.0, 0.0, 0.0);
+
+        // Its own material
+        let material = Rc::new(textured(&mut backend));
+        let label =
+            Label::new(&mut backend, origin, 4.0, 2.0, material).unwrap();
+        label.delete(&mut backend);
+        assert!(backend.live_buffers().is_empty());
+        assert_eq!(deleted_textures(&backend), 1);
+
+        // A material shared with another label
+        let material = Rc::new(textured(&mut backend));
+        let first =
+            Label::new(&mut backend, origin, 4.0, 2.0, material.clone())
+                .unwrap();
+        let second =
+            Label::new(&mut backend, origin, 4.0, 2.0, material).unwrap();
+        first.delete(&mut backend);
+        assert_eq!(backend.live_buffers().len(), 2);
+        assert_eq!(deleted_textures(&backend), 1);
+        second.delete(&mut backend);
+        assert!(backend.live_buffers().is_empty());
+        assert_eq!(deleted_textures(&backend), 2);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::colour::Colour;
+use crate::vector::Vec3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    // Reaches every surface evenly, whichever way it faces
+    Ambient,
+    // Infinitely far away, like the sun. The vector is the direction the
+    // light travels in.
+    Directional(Vec3),
+    // Shines in every direction from a point in world space
+    Point(Vec3),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub kind:      LightKind,
+    pub colour:    Colour,
+    pub intensity: f32,
+}
+
+#[allow(dead_code)]
+impl Light {
+    pub fn ambient(intensity: f32) -> Light {
+        Light {
+            kind: LightKind::Ambient,
+            colour: Colour::WHITE,
+            intensity,
+        }
+    }
+
+    pub fn directional(direction: Vec3, intensity: f32) -> Light {
+        Light {
+            kind: LightKind::Directional(direction.normalize()),
+            colour: Colour::WHITE,
+            intensity,
+        }
+    }
+
+    pub fn point(position: Vec3, intensity: f32) -> Light {
+        Light {
+            kind: LightKind::Point(position),
+            colour: Colour::WHITE,
+            intensity,
+        }
+    }
+
+    pub fn with_colour(mut self, colour: Colour) -> Light {
+        self.colour = colour;
+        return self;
+    }
+
+    // Colour scaled by intensity, what the shader receives
+    pub fn radiance(&self) -> Vec3 {
+        Vec3::new(self.colour.r, self.colour.g, self.colour.b) * self.intensity
+    }
+
+    // A dim ambient light and a light from the top right, over the viewer's
+    // shoulder
+    pub fn default_rig() -> Vec<Light> {
+        vec![
+            Light::ambient(0.2),
+            Light::directional(Vec3::new(-1.0, -1.0, -1.0), 0.8),
+        ]
+    }
+}
@@ -0,0 +1,144 @@
+use std::rc::Rc;
+
+use crate::backend::{Backend, Uniform};
+use crate::colour::Colour;
+use crate::error::SpatioError;
+use crate::light::Light;
+use crate::program::Program;
+use crate::texture::Texture;
+
+// Uniforms a program needs for primitives to be drawn with it
+pub const MATERIAL_UNIFORMS: [&str; 4] =
+    ["u_model", "u_view", "u_projection", "colour"];
+
+// How a primitive looks: the program it's drawn with and the values of that
+// program's uniforms. Primitives hold it through an `Rc`, so one material
+// can be shared by many of them and one program by many materials.
+#[derive(Clone)]
+pub struct Material {
+    pub colour:      Colour,
+    // Colour of the edges drawn over the faces in `SolidWithEdges` mode
+    pub edge_colour: Colour,
+    // Strength of the highlights, 0 for a matte, purely Lambert surface
+    pub specular:    f32,
+    // Phong exponent, the higher the smaller and sharper the highlights
+    pub shininess:   f32,
+    // None writes depth for opaque materials only, so transparent ones don't
+    // hide what's behind them but drawn after them
+    pub depth_write: Option<bool>,
+    // Multiplies the colour, sampled at the mesh's UVs
+    pub texture:     Option<Rc<Texture>>,
+    program:         Rc<Program>,
+}
+
+#[allow(dead_code)]
+impl Material {
+    // Fails when the program lacks one of `MATERIAL_UNIFORMS`
+    pub fn new(
+        program: Rc<Program>,
+        colour: Colour,
+    ) -> Result<Material, SpatioError> {
+        program.require_uniforms(&MATERIAL_UNIFORMS)?;
+
+        return Ok(Material {
+            colour,
+            edge_colour: Colour::BLACK,
+            specular: 0.3,
+            shininess: 32.0,
+            depth_write: None,
+            texture: None,
+            program,
+        });
+    }
+
+    // White, so the texture shows as it is. Fails when the program can't
+    // sample textures.
+    pub fn textured(
+        program: Rc<Program>,
+        texture: Rc<Texture>,
+    ) -> Result<Material, SpatioError> {
+        return Material::new(program, Colour::WHITE)?.with_texture(texture);
+    }
+
+    pub fn with_texture(
+        mut self,
+        texture: Rc<Texture>,
+    ) -> Result<Material, SpatioError> {
+        self.program
+            .require_uniforms(&["u_texture", "u_textured"])?;
+        self.texture = Some(texture);
+        return Ok(self);
+    }
+
+    pub fn with_specular(mut self, specular: f32, shininess: f32) -> Material {
+        self.specular = specular;
+        self.shininess = shininess;
+        return self;
+    }
+
+    pub fn with_depth_write(mut self, depth_write: bool) -> Material {
+        self.depth_write = Some(depth_write);
+        return self;
+    }
+
+    // Transparent materials are blended, and drawn after the opaque ones.
+    // Textures with transparent pixels make the material transparent too.
+    pub fn is_transparent(&self) -> bool {
+        let texture_transparent = self
+            .texture
+            .as_ref()
+            .is_some_and(|texture| !texture.is_opaque());
+        return self.colour.a < 1.0 || texture_transparent;
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    // Makes the program current and sets the material's uniforms, along with
+    // the lights when the program is lit, and the blend and depth write
+    // state. The matrices are left to the primitive.
+    pub fn apply(
+        &self,
+        backend: &mut dyn Backend,
+        lights: &[Light],
+    ) -> Result<(), SpatioError> {
+        let transparent = self.is_transparent();
+        backend.set_blending(transparent);
+        backend.set_depth_write(self.depth_write.unwrap_or(!transparent));
+
+        self.program.use_program(backend);
+        self.program
+            .set_uniform(backend, "colour", self.colour.into())?;
+
+        if self.program.has_uniform("u_specular") {
+            self.program.set_uniform(
+                backend,
+                "u_specular",
+                Uniform::Float(self.specular),
+            )?;
+        }
+        if self.program.has_uniform("u_shininess") {
+            self.program.set_uniform(
+                backend,
+                "u_shininess",
+                Uniform::Float(self.shininess),
+            )?;
+        }
+        if self.program.has_uniform("u_textured") {
+            self.program.set_uniform(
+                backend,
+                "u_textured",
+                Uniform::Int(self.texture.is_some() as i32),
+            )?;
+        }
+        if let Some(texture) = &self.texture {
+            texture.bind(backend, 0);
+            self.program
+                .set_uniform(backend, "u_texture", Uniform::Int(0))?;
+        }
+        self.program.set_lights(backend, lights)?;
+
+        return Ok(());
+    }
+}
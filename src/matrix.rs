@@ -1,8 +1,15 @@
+use std::ops::{Add, Mul, MulAssign, Neg, Sub};
+
 use crate::log;
+use crate::vector::{Vec3, Vec4, Vertex};
 
 pub type Matrix4F = [f32; 16];
+#[allow(dead_code)]
 pub type Vector4F = [f32; 4];
+pub type Matrix3F = [f32; 9];
 
+// The x and y rotations turn clockwise looking down their axis, against the
+// right hand rule that the z one, `Mat4::rotation_*` and `Quaternion` follow
 pub fn rotate_x_matrix(angle: f32) -> Matrix4F {
     [
         1.,
@@ -107,6 +114,64 @@ pub fn perspective_matrix(
     ];
 }
 
+pub fn orthographic_matrix(
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    near: f32,
+    far: f32,
+) -> Matrix4F {
+    let width_inverse = (right - left).recip();
+    let height_inverse = (top - bottom).recip();
+    let range_inverse = (near - far).recip();
+
+    return [
+        2.0 * width_inverse,
+        0.,
+        0.,
+        0.,
+        0.,
+        2.0 * height_inverse,
+        0.,
+        0.,
+        0.,
+        0.,
+        2.0 * range_inverse,
+        0.,
+        -(right + left) * width_inverse,
+        -(top + bottom) * height_inverse,
+        (near + far) * range_inverse,
+        1.,
+    ];
+}
+
+// View matrix placing the camera at `eye`, looking at `target`
+pub fn look_at_matrix(eye: Vec3, target: Vec3, up: Vec3) -> Matrix4F {
+    let z_axis = (eye - target).normalize();
+    let x_axis = up.cross(z_axis).normalize();
+    let y_axis = z_axis.cross(x_axis);
+
+    return [
+        x_axis.x,
+        y_axis.x,
+        z_axis.x,
+        0.,
+        x_axis.y,
+        y_axis.y,
+        z_axis.y,
+        0.,
+        x_axis.z,
+        y_axis.z,
+        z_axis.z,
+        0.,
+        -x_axis.dot(eye),
+        -y_axis.dot(eye),
+        -z_axis.dot(eye),
+        1.,
+    ];
+}
+
 pub fn mat_mul(a: &Matrix4F, b: &Matrix4F) -> Matrix4F {
     let a00 = a[0];
     let a01 = a[1];
@@ -162,6 +227,7 @@ pub fn mat_mul(a: &Matrix4F, b: &Matrix4F) -> Matrix4F {
     ];
 }
 
+#[allow(dead_code)]
 pub fn mat_mul_many(matrices: &[Matrix4F]) -> Matrix4F {
     let mut output = ID_MATRIX;
     for matrix in matrices {
@@ -175,6 +241,205 @@ pub const ID_MATRIX: Matrix4F = [
     1.0,
 ];
 
+pub const ID_MATRIX3: Matrix3F = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+
+pub fn mat_transpose(mat: &Matrix4F) -> Matrix4F {
+    [
+        mat[0], mat[4], mat[8], mat[12], mat[1], mat[5], mat[9], mat[13],
+        mat[2], mat[6], mat[10], mat[14], mat[3], mat[7], mat[11], mat[15],
+    ]
+}
+
+pub fn mat_determinant(mat: &Matrix4F) -> f32 {
+    let a00 = mat[0];
+    let a01 = mat[1];
+    let a02 = mat[2];
+    let a03 = mat[3];
+    let a10 = mat[4];
+    let a11 = mat[5];
+    let a12 = mat[6];
+    let a13 = mat[7];
+    let a20 = mat[8];
+    let a21 = mat[9];
+    let a22 = mat[10];
+    let a23 = mat[11];
+    let a30 = mat[12];
+    let a31 = mat[13];
+    let a32 = mat[14];
+    let a33 = mat[15];
+
+    let b00 = a00 * a11 - a01 * a10;
+    let b01 = a00 * a12 - a02 * a10;
+    let b02 = a00 * a13 - a03 * a10;
+    let b03 = a01 * a12 - a02 * a11;
+    let b04 = a01 * a13 - a03 * a11;
+    let b05 = a02 * a13 - a03 * a12;
+    let b06 = a20 * a31 - a21 * a30;
+    let b07 = a20 * a32 - a22 * a30;
+    let b08 = a20 * a33 - a23 * a30;
+    let b09 = a21 * a32 - a22 * a31;
+    let b10 = a21 * a33 - a23 * a31;
+    let b11 = a22 * a33 - a23 * a32;
+
+    return b00 * b11 - b01 * b10 + b02 * b09 + b03 * b08 - b04 * b07 +
+        b05 * b06;
+}
+
+// Returns None when the matrix is singular
+pub fn mat_inverse(mat: &Matrix4F) -> Option<Matrix4F> {
+    let a00 = mat[0];
+    let a01 = mat[1];
+    let a02 = mat[2];
+    let a03 = mat[3];
+    let a10 = mat[4];
+    let a11 = mat[5];
+    let a12 = mat[6];
+    let a13 = mat[7];
+    let a20 = mat[8];
+    let a21 = mat[9];
+    let a22 = mat[10];
+    let a23 = mat[11];
+    let a30 = mat[12];
+    let a31 = mat[13];
+    let a32 = mat[14];
+    let a33 = mat[15];
+
+    let b00 = a00 * a11 - a01 * a10;
+    let b01 = a00 * a12 - a02 * a10;
+    let b02 = a00 * a13 - a03 * a10;
+    let b03 = a01 * a12 - a02 * a11;
+    let b04 = a01 * a13 - a03 * a11;
+    let b05 = a02 * a13 - a03 * a12;
+    let b06 = a20 * a31 - a21 * a30;
+    let b07 = a20 * a32 - a22 * a30;
+    let b08 = a20 * a33 - a23 * a30;
+    let b09 = a21 * a32 - a22 * a31;
+    let b10 = a21 * a33 - a23 * a31;
+    let b11 = a22 * a33 - a23 * a32;
+
+    let det =
+        b00 * b11 - b01 * b10 + b02 * b09 + b03 * b08 - b04 * b07 + b05 * b06;
+
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+
+    let det = det.recip();
+
+    return Some([
+        (a11 * b11 - a12 * b10 + a13 * b09) * det,
+        (a02 * b10 - a01 * b11 - a03 * b09) * det,
+        (a31 * b05 - a32 * b04 + a33 * b03) * det,
+        (a22 * b04 - a21 * b05 - a23 * b03) * det,
+        (a12 * b08 - a10 * b11 - a13 * b07) * det,
+        (a00 * b11 - a02 * b08 + a03 * b07) * det,
+        (a32 * b02 - a30 * b05 - a33 * b01) * det,
+        (a20 * b05 - a22 * b02 + a23 * b01) * det,
+        (a10 * b10 - a11 * b08 + a13 * b06) * det,
+        (a01 * b08 - a00 * b10 - a03 * b06) * det,
+        (a30 * b04 - a31 * b02 + a33 * b00) * det,
+        (a21 * b02 - a20 * b04 - a23 * b00) * det,
+        (a11 * b07 - a10 * b09 - a12 * b06) * det,
+        (a00 * b09 - a01 * b07 + a02 * b06) * det,
+        (a31 * b01 - a30 * b03 - a32 * b00) * det,
+        (a20 * b03 - a21 * b01 + a22 * b00) * det,
+    ]);
+}
+
+// Cheaper inverse for matrices made only of rotations, scales and
+// translations (i.e. bottom row is 0 0 0 1). Don't use it on projections.
+pub fn mat_affine_inverse(mat: &Matrix4F) -> Option<Matrix4F> {
+    let linear = mat3_inverse(&mat_upper_left(mat))?;
+
+    let tx = mat[12];
+    let ty = mat[13];
+    let tz = mat[14];
+
+    return Some([
+        linear[0],
+        linear[1],
+        linear[2],
+        0.,
+        linear[3],
+        linear[4],
+        linear[5],
+        0.,
+        linear[6],
+        linear[7],
+        linear[8],
+        0.,
+        -(linear[0] * tx + linear[3] * ty + linear[6] * tz),
+        -(linear[1] * tx + linear[4] * ty + linear[7] * tz),
+        -(linear[2] * tx + linear[5] * ty + linear[8] * tz),
+        1.,
+    ]);
+}
+
+// Inverse transpose of the upper 3x3 of a model(-view) matrix, used to
+// transform normals so they stay perpendicular under non-uniform scaling
+pub fn normal_matrix(mat: &Matrix4F) -> Option<Matrix3F> {
+    let inverse = mat3_inverse(&mat_upper_left(mat))?;
+    return Some(mat3_transpose(&inverse));
+}
+
+// Column-major like `Matrix4F`
+pub fn mat3_vec_mul(mat: &Matrix3F, vec: Vec3) -> Vec3 {
+    Vec3::new(
+        mat[0] * vec.x + mat[3] * vec.y + mat[6] * vec.z,
+        mat[1] * vec.x + mat[4] * vec.y + mat[7] * vec.z,
+        mat[2] * vec.x + mat[5] * vec.y + mat[8] * vec.z,
+    )
+}
+
+pub fn mat_upper_left(mat: &Matrix4F) -> Matrix3F {
+    [
+        mat[0], mat[1], mat[2], mat[4], mat[5], mat[6], mat[8], mat[9], mat[10],
+    ]
+}
+
+pub fn mat3_transpose(mat: &Matrix3F) -> Matrix3F {
+    [
+        mat[0], mat[3], mat[6], mat[1], mat[4], mat[7], mat[2], mat[5], mat[8],
+    ]
+}
+
+pub fn mat3_inverse(mat: &Matrix3F) -> Option<Matrix3F> {
+    let a00 = mat[0];
+    let a01 = mat[1];
+    let a02 = mat[2];
+    let a10 = mat[3];
+    let a11 = mat[4];
+    let a12 = mat[5];
+    let a20 = mat[6];
+    let a21 = mat[7];
+    let a22 = mat[8];
+
+    let b01 = a22 * a11 - a12 * a21;
+    let b11 = -a22 * a10 + a12 * a20;
+    let b21 = a21 * a10 - a11 * a20;
+
+    let det = a00 * b01 + a01 * b11 + a02 * b21;
+
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+
+    let det = det.recip();
+
+    return Some([
+        b01 * det,
+        (-a22 * a01 + a02 * a21) * det,
+        (a12 * a01 - a02 * a11) * det,
+        b11 * det,
+        (a22 * a00 - a02 * a20) * det,
+        (-a12 * a00 + a02 * a10) * det,
+        b21 * det,
+        (-a21 * a00 + a01 * a20) * det,
+        (a11 * a00 - a01 * a10) * det,
+    ]);
+}
+
+#[allow(dead_code)]
 pub fn mat_vec_mul(mat: Matrix4F, vec: Vector4F) -> Vector4F {
     [
         mat[0] * vec[0] + mat[4] * vec[1] + mat[8] * vec[2] + mat[12] * vec[3],
@@ -184,6 +449,7 @@ pub fn mat_vec_mul(mat: Matrix4F, vec: Vector4F) -> Vector4F {
     ]
 }
 
+#[allow(dead_code)]
 pub fn vec_scalar_div(vec: Vector4F, scalar: f32) -> Vector4F {
     return [
         vec[0] / scalar,
@@ -193,6 +459,7 @@ pub fn vec_scalar_div(vec: Vector4F, scalar: f32) -> Vector4F {
     ];
 }
 
+#[allow(dead_code)]
 pub fn print_matrix(mat: &Matrix4F) {
     log(&format!(
         r"
@@ -220,9 +487,762 @@ pub fn print_matrix(mat: &Matrix4F) {
     ));
 }
 
+#[allow(dead_code)]
 pub fn print_vector(vec: &Vector4F) {
     log(&format!(
         " | {:.2} {:.2} {:.2} {:.2} | ",
         vec[0], vec[1], vec[2], vec[3]
     ));
 }
+
+// Column-major 4x4 matrix, laid out exactly like `Matrix4F` so it can be handed
+// straight to `uniform_matrix4fv`. `a * b` applies `b` first, then `a`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4(pub Matrix4F);
+
+#[allow(dead_code)]
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4(ID_MATRIX);
+
+    pub fn from_cols(c0: Vec4, c1: Vec4, c2: Vec4, c3: Vec4) -> Mat4 {
+        Mat4([
+            c0.x, c0.y, c0.z, c0.w, c1.x, c1.y, c1.z, c1.w, c2.x, c2.y, c2.z,
+            c2.w, c3.x, c3.y, c3.z, c3.w,
+        ])
+    }
+
+    pub fn from_rows(r0: Vec4, r1: Vec4, r2: Vec4, r3: Vec4) -> Mat4 {
+        Mat4([
+            r0.x, r1.x, r2.x, r3.x, r0.y, r1.y, r2.y, r3.y, r0.z, r1.z, r2.z,
+            r3.z, r0.w, r1.w, r2.w, r3.w,
+        ])
+    }
+
+    pub fn translation(offset: Vec3) -> Mat4 {
+        Mat4(translate_matrix(offset.x, offset.y, offset.z))
+    }
+
+    pub fn scale(factors: Vec3) -> Mat4 {
+        Mat4(scale_matrix(factors.x, factors.y, factors.z))
+    }
+
+    // Counter-clockwise looking down the axis towards the origin, like
+    // `Quaternion::from_axis_angle`
+    pub fn rotation_x(angle: f32) -> Mat4 {
+        Mat4(rotate_x_matrix(-angle))
+    }
+
+    pub fn rotation_y(angle: f32) -> Mat4 {
+        Mat4(rotate_y_matrix(-angle))
+    }
+
+    pub fn rotation_z(angle: f32) -> Mat4 {
+        Mat4(rotate_z_matrix(angle))
+    }
+
+    pub fn perspective(
+        fov: f32,
+        aspect_ratio: f32,
+        near: f32,
+        far: f32,
+    ) -> Mat4 {
+        Mat4(perspective_matrix(fov, aspect_ratio, near, far))
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f32 {
+        self.0[col * 4 + row]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: f32) {
+        self.0[col * 4 + row] = value;
+    }
+
+    pub fn col(&self, index: usize) -> Vec4 {
+        let m = &self.0;
+        let i = index * 4;
+        Vec4::new(m[i], m[i + 1], m[i + 2], m[i + 3])
+    }
+
+    pub fn row(&self, index: usize) -> Vec4 {
+        let m = &self.0;
+        Vec4::new(m[index], m[index + 4], m[index + 8], m[index + 12])
+    }
+
+    // Transforms a position, applying translation and perspective divide
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        (*self * point.extend(1.0)).perspective_divide()
+    }
+
+    // Transforms a direction, ignoring translation
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        (*self * vector.extend(0.0)).xyz()
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        Mat4(mat_transpose(&self.0))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        mat_determinant(&self.0)
+    }
+
+    pub fn inverse(&self) -> Option<Mat4> {
+        mat_inverse(&self.0).map(Mat4)
+    }
+
+    pub fn affine_inverse(&self) -> Option<Mat4> {
+        mat_affine_inverse(&self.0).map(Mat4)
+    }
+
+    pub fn normal_matrix(&self) -> Option<Matrix3F> {
+        normal_matrix(&self.0)
+    }
+
+    pub fn to_array(self) -> Matrix4F {
+        self.0
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Mat4 {
+        Mat4::IDENTITY
+    }
+}
+
+impl From<Matrix4F> for Mat4 {
+    fn from(matrix: Matrix4F) -> Mat4 {
+        Mat4(matrix)
+    }
+}
+
+impl From<Mat4> for Matrix4F {
+    fn from(matrix: Mat4) -> Matrix4F {
+        matrix.0
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, other: Mat4) -> Mat4 {
+        Mat4(mat_mul(&self.0, &other.0))
+    }
+}
+
+impl MulAssign for Mat4 {
+    fn mul_assign(&mut self, other: Mat4) {
+        *self = *self * other;
+    }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+
+    fn mul(self, vec: Vec4) -> Vec4 {
+        Vec4::from(mat_vec_mul(self.0, vec.to_array()))
+    }
+}
+
+impl Mul<f32> for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, scalar: f32) -> Mat4 {
+        Mat4(self.0.map(|value| value * scalar))
+    }
+}
+
+impl Add for Mat4 {
+    type Output = Mat4;
+
+    fn add(self, other: Mat4) -> Mat4 {
+        let mut output = self.0;
+        for (value, other) in output.iter_mut().zip(other.0) {
+            *value += other;
+        }
+        Mat4(output)
+    }
+}
+
+impl Sub for Mat4 {
+    type Output = Mat4;
+
+    fn sub(self, other: Mat4) -> Mat4 {
+        self + -other
+    }
+}
+
+impl Neg for Mat4 {
+    type Output = Mat4;
+
+    fn neg(self) -> Mat4 {
+        self * -1.0
+    }
+}
+
+// Unit quaternion rotation. Follows the right-hand rule: a positive angle
+// around an axis pointing at the viewer rotates counter-clockwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+#[allow(dead_code)]
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+        w: 1.0,
+    };
+
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Quaternion {
+        Quaternion {
+            x,
+            y,
+            z,
+            w,
+        }
+    }
+
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Quaternion {
+        let axis = axis.normalize();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Quaternion::new(axis.x * sin, axis.y * sin, axis.z * sin, cos)
+    }
+
+    // Angles are applied around X first, then Y, then Z (all world axes)
+    pub fn from_euler(x: f32, y: f32, z: f32) -> Quaternion {
+        let (sx, cx) = (x / 2.0).sin_cos();
+        let (sy, cy) = (y / 2.0).sin_cos();
+        let (sz, cz) = (z / 2.0).sin_cos();
+
+        Quaternion::new(
+            sx * cy * cz - cx * sy * sz,
+            cx * sy * cz + sx * cy * sz,
+            cx * cy * sz - sx * sy * cz,
+            cx * cy * cz + sx * sy * sz,
+        )
+    }
+
+    // Extracts the rotation part of a matrix. Scale must be removed first.
+    pub fn from_matrix(mat: &Matrix4F) -> Quaternion {
+        let m00 = mat[0];
+        let m10 = mat[1];
+        let m20 = mat[2];
+        let m01 = mat[4];
+        let m11 = mat[5];
+        let m21 = mat[6];
+        let m02 = mat[8];
+        let m12 = mat[9];
+        let m22 = mat[10];
+
+        let trace = m00 + m11 + m22;
+
+        let q = if trace > 0.0 {
+            let s = 0.5 / (trace + 1.0).sqrt();
+            Quaternion::new(
+                (m21 - m12) * s,
+                (m02 - m20) * s,
+                (m10 - m01) * s,
+                0.25 / s,
+            )
+        } else if m00 > m11 && m00 > m22 {
+            let s = 2.0 * (1.0 + m00 - m11 - m22).sqrt();
+            Quaternion::new(
+                0.25 * s,
+                (m01 + m10) / s,
+                (m02 + m20) / s,
+                (m21 - m12) / s,
+            )
+        } else if m11 > m22 {
+            let s = 2.0 * (1.0 + m11 - m00 - m22).sqrt();
+            Quaternion::new(
+                (m01 + m10) / s,
+                0.25 * s,
+                (m12 + m21) / s,
+                (m02 - m20) / s,
+            )
+        } else {
+            let s = 2.0 * (1.0 + m22 - m00 - m11).sqrt();
+            Quaternion::new(
+                (m02 + m20) / s,
+                (m12 + m21) / s,
+                0.25 * s,
+                (m10 - m01) / s,
+            )
+        };
+
+        return q.normalize();
+    }
+
+    pub fn to_matrix(self) -> Matrix4F {
+        let Quaternion {
+            x,
+            y,
+            z,
+            w,
+        } = self;
+
+        let xx = x * x;
+        let yy = y * y;
+        let zz = z * z;
+        let xy = x * y;
+        let xz = x * z;
+        let yz = y * z;
+        let wx = w * x;
+        let wy = w * y;
+        let wz = w * z;
+
+        return [
+            1. - 2. * (yy + zz),
+            2. * (xy + wz),
+            2. * (xz - wy),
+            0.,
+            2. * (xy - wz),
+            1. - 2. * (xx + zz),
+            2. * (yz + wx),
+            0.,
+            2. * (xz + wy),
+            2. * (yz - wx),
+            1. - 2. * (xx + yy),
+            0.,
+            0.,
+            0.,
+            0.,
+            1.,
+        ];
+    }
+
+    // Inverse of `from_euler`, returned as (x, y, z)
+    pub fn to_euler(self) -> (f32, f32, f32) {
+        let Quaternion {
+            x,
+            y,
+            z,
+            w,
+        } = self;
+
+        let roll = (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y));
+        let pitch = (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin();
+        let yaw = (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z));
+
+        return (roll, pitch, yaw);
+    }
+
+    pub fn to_axis_angle(self) -> (Vec3, f32) {
+        let q = self.normalize();
+        let angle = 2.0 * q.w.clamp(-1.0, 1.0).acos();
+        let sin = (1.0 - q.w * q.w).max(0.0).sqrt();
+
+        if sin < 1e-6 {
+            return (Vec3::X, 0.0);
+        }
+
+        return (Vec3::new(q.x, q.y, q.z) / sin, angle);
+    }
+
+    pub fn dot(self, other: Quaternion) -> f32 {
+        self.x * other.x +
+            self.y * other.y +
+            self.z * other.z +
+            self.w * other.w
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(self) -> Quaternion {
+        let length = self.length();
+        if length == 0.0 {
+            return Quaternion::IDENTITY;
+        }
+        return Quaternion::new(
+            self.x / length,
+            self.y / length,
+            self.z / length,
+            self.w / length,
+        );
+    }
+
+    pub fn conjugate(self) -> Quaternion {
+        Quaternion::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    pub fn inverse(self) -> Quaternion {
+        let length_squared = self.dot(self);
+        let c = self.conjugate();
+        return Quaternion::new(
+            c.x / length_squared,
+            c.y / length_squared,
+            c.z / length_squared,
+            c.w / length_squared,
+        );
+    }
+
+    pub fn rotate(self, vec: Vec3) -> Vec3 {
+        // v' = v + 2w(q x v) + 2(q x (q x v))
+        let q = Vec3::new(self.x, self.y, self.z);
+        let t = q.cross(vec) * 2.0;
+        return vec + t * self.w + q.cross(t);
+    }
+
+    pub fn rotate_vertex(self, vertex: Vertex) -> Vertex {
+        Vertex::from(self.rotate(Vec3::from(vertex)))
+    }
+
+    // Normalised linear interpolation: cheaper than slerp, constant velocity
+    // is lost but the path is the same
+    pub fn nlerp(self, other: Quaternion, t: f32) -> Quaternion {
+        let other = if self.dot(other) < 0.0 { -other } else { other };
+        return Quaternion::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+            self.z + (other.z - self.z) * t,
+            self.w + (other.w - self.w) * t,
+        )
+        .normalize();
+    }
+
+    // Spherical linear interpolation, always along the shortest arc
+    pub fn slerp(self, other: Quaternion, t: f32) -> Quaternion {
+        let mut cos = self.dot(other);
+        let mut other = other;
+
+        if cos < 0.0 {
+            other = -other;
+            cos = -cos;
+        }
+
+        // Nearly parallel, sin(theta) ~ 0 so fall back to nlerp
+        if cos > 0.9995 {
+            return self.nlerp(other, t);
+        }
+
+        let theta = cos.acos();
+        let sin = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin;
+        let b = (t * theta).sin() / sin;
+
+        return Quaternion::new(
+            self.x * a + other.x * b,
+            self.y * a + other.y * b,
+            self.z * a + other.z * b,
+            self.w * a + other.w * b,
+        );
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Quaternion {
+        Quaternion::IDENTITY
+    }
+}
+
+impl From<Quaternion> for Matrix4F {
+    fn from(q: Quaternion) -> Matrix4F {
+        q.to_matrix()
+    }
+}
+
+impl From<Quaternion> for Mat4 {
+    fn from(q: Quaternion) -> Mat4 {
+        Mat4(q.to_matrix())
+    }
+}
+
+// `a * b` rotates by `b` first, then by `a`, like matrices
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, other: Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * other.x + self.x * other.w + self.y * other.z -
+                self.z * other.y,
+            self.w * other.y - self.x * other.z +
+                self.y * other.w +
+                self.z * other.x,
+            self.w * other.z + self.x * other.y - self.y * other.x +
+                self.z * other.w,
+            self.w * other.w -
+                self.x * other.x -
+                self.y * other.y -
+                self.z * other.z,
+        )
+    }
+}
+
+impl MulAssign for Quaternion {
+    fn mul_assign(&mut self, other: Quaternion) {
+        *self = *self * other;
+    }
+}
+
+impl Mul<Vec3> for Quaternion {
+    type Output = Vec3;
+
+    fn mul(self, vec: Vec3) -> Vec3 {
+        self.rotate(vec)
+    }
+}
+
+impl Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Quaternion {
+        Quaternion::new(-self.x, -self.y, -self.z, -self.w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_mat_eq(a: Mat4, b: Mat4) {
+        for (x, y) in a.0.iter().zip(b.0) {
+            assert!((x - y).abs() < EPSILON, "{:?} != {:?}", a, b);
+        }
+    }
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        assert!(a.distance(b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn rotations_are_right_handed() {
+        let quarter = FRAC_PI_2;
+        assert_vec_eq(
+            Mat4::rotation_x(quarter).transform_vector(Vec3::Y),
+            Vec3::Z,
+        );
+        assert_vec_eq(
+            Mat4::rotation_y(quarter).transform_vector(Vec3::Z),
+            Vec3::X,
+        );
+        assert_vec_eq(
+            Mat4::rotation_z(quarter).transform_vector(Vec3::X),
+            Vec3::Y,
+        );
+
+        for angle in [0.3, -1.2, 2.5] {
+            for (axis, matrix) in [
+                (Vec3::X, Mat4::rotation_x(angle)),
+                (Vec3::Y, Mat4::rotation_y(angle)),
+                (Vec3::Z, Mat4::rotation_z(angle)),
+            ] {
+                let q = Quaternion::from_axis_angle(axis, angle);
+                assert_mat_eq(Mat4::from(q), matrix);
+            }
+        }
+    }
+
+    #[test]
+    fn legacy_x_and_y_rotations_are_clockwise() {
+        let angle = 0.7;
+        assert_mat_eq(Mat4(rotate_x_matrix(-angle)), Mat4::rotation_x(angle));
+        assert_mat_eq(Mat4(rotate_y_matrix(-angle)), Mat4::rotation_y(angle));
+        assert_mat_eq(Mat4(rotate_z_matrix(angle)), Mat4::rotation_z(angle));
+    }
+
+    // Rotation, non-uniform scale and translation
+    fn affine() -> Mat4 {
+        Mat4::translation(Vec3::new(1.0, -2.0, 3.0)) *
+            Mat4::from(Quaternion::from_axis_angle(
+                Vec3::new(1.0, 2.0, -0.5),
+                0.8,
+            )) *
+            Mat4::scale(Vec3::new(2.0, 0.5, 3.0))
+    }
+
+    #[test]
+    fn inverse() {
+        let projection = Mat4::perspective(1.0, 1.5, 0.1, 100.0);
+        for m in [affine(), projection, projection * affine()] {
+            let inverse = m.inverse().unwrap();
+            assert_mat_eq(m * inverse, Mat4::IDENTITY);
+            assert_mat_eq(inverse * m, Mat4::IDENTITY);
+        }
+    }
+
+    #[test]
+    fn singular_has_no_inverse() {
+        let flat = Mat4::scale(Vec3::new(1.0, 0.0, 1.0));
+        assert_eq!(flat.inverse(), None);
+        assert_eq!(flat.affine_inverse(), None);
+        assert_eq!(flat.normal_matrix(), None);
+    }
+
+    #[test]
+    fn affine_inverse_matches_inverse() {
+        let m = affine();
+        assert_mat_eq(m.affine_inverse().unwrap(), m.inverse().unwrap());
+    }
+
+    #[test]
+    fn determinant() {
+        assert_eq!(Mat4::IDENTITY.determinant(), 1.0);
+        assert!((affine().determinant() - 3.0).abs() < EPSILON);
+
+        let mirror = Mat4::scale(Vec3::new(-1.0, 1.0, 1.0));
+        assert_eq!(mirror.determinant(), -1.0);
+    }
+
+    #[test]
+    fn transpose() {
+        let m = affine();
+        assert_eq!(m.transpose().transpose(), m);
+        assert_eq!(m.transpose().get(3, 0), m.get(0, 3));
+        assert_eq!(m.transpose().row(2), m.col(2));
+    }
+
+    #[test]
+    fn normal_matrix_keeps_normals_perpendicular() {
+        let m = affine();
+        let normal_matrix = m.normal_matrix().unwrap();
+
+        // A surface along `tangent`, with `normal` perpendicular to it
+        let tangent = Vec3::new(1.0, 1.0, 0.0);
+        let normal = Vec3::new(1.0, -1.0, 2.0);
+        assert_eq!(tangent.dot(normal), 0.0);
+
+        let tangent = m.transform_vector(tangent);
+        let normal = mat3_vec_mul(&normal_matrix, normal);
+        assert!(tangent.dot(normal).abs() < EPSILON);
+    }
+
+    #[test]
+    fn transform_point_and_vector() {
+        let m = Mat4::translation(Vec3::new(1.0, 2.0, 3.0)) *
+            Mat4::scale(Vec3::new(2.0, 2.0, 2.0));
+
+        assert_vec_eq(m.transform_point(Vec3::X), Vec3::new(3.0, 2.0, 3.0));
+        assert_vec_eq(m.transform_vector(Vec3::X), Vec3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn quaternion_matrix_round_trip() {
+        // Angles past a half turn and axes of every sign, to go through each
+        // branch of `from_matrix`
+        for (axis, angle) in [
+            (Vec3::new(1.0, 2.0, 3.0), 0.5),
+            (Vec3::X, 3.0),
+            (Vec3::Y, 3.0),
+            (Vec3::Z, 3.0),
+            (Vec3::new(-1.0, 0.5, -2.0), -2.0),
+        ] {
+            let q = Quaternion::from_axis_angle(axis, angle);
+            let back = Quaternion::from_matrix(&q.to_matrix());
+
+            // q and -q are the same rotation
+            assert!((q.dot(back).abs() - 1.0).abs() < EPSILON);
+            assert_mat_eq(Mat4::from(back), Mat4::from(q));
+        }
+    }
+
+    #[test]
+    fn quaternion_rotates_like_its_matrix() {
+        let q = Quaternion::from_axis_angle(Vec3::new(0.3, -1.0, 0.2), 1.1);
+        let v = Vec3::new(1.0, 2.0, 3.0);
+        assert_vec_eq(q * v, Mat4::from(q).transform_vector(v));
+        assert_vec_eq(q.inverse() * (q * v), v);
+    }
+
+    #[test]
+    fn axis_angle_round_trip() {
+        let axis = Vec3::new(1.0, -2.0, 2.0);
+        let q = Quaternion::from_axis_angle(axis, 1.3);
+        let (back_axis, angle) = q.to_axis_angle();
+
+        assert_vec_eq(back_axis, axis.normalize());
+        assert!((angle - 1.3).abs() < EPSILON);
+    }
+
+    #[test]
+    fn euler_round_trip() {
+        let (x, y, z) = (0.4, -0.9, 2.1);
+        let q = Quaternion::from_euler(x, y, z);
+        let (bx, by, bz) = q.to_euler();
+        assert!((bx - x).abs() < EPSILON);
+        assert!((by - y).abs() < EPSILON);
+        assert!((bz - z).abs() < EPSILON);
+
+        // X first, then Y, then Z
+        let rotations =
+            Mat4::rotation_z(z) * Mat4::rotation_y(y) * Mat4::rotation_x(x);
+        assert_mat_eq(Mat4::from(q), rotations);
+    }
+
+    #[test]
+    fn slerp_endpoints_and_midpoint() {
+        let from = Quaternion::from_axis_angle(Vec3::Z, 0.2);
+        let to = Quaternion::from_axis_angle(Vec3::Z, 1.8);
+
+        assert_mat_eq(Mat4::from(from.slerp(to, 0.0)), Mat4::from(from));
+        assert_mat_eq(Mat4::from(from.slerp(to, 1.0)), Mat4::from(to));
+        assert_mat_eq(Mat4::from(from.slerp(to, 0.5)), Mat4::rotation_z(1.0));
+
+        // The shortest way round, even when `to` is given negated
+        assert_mat_eq(Mat4::from(from.slerp(-to, 1.0)), Mat4::from(to));
+    }
+
+    #[test]
+    fn look_at() {
+        let eye = Vec3::new(3.0, 4.0, 5.0);
+        let target = Vec3::new(1.0, 0.0, -1.0);
+        let view = Mat4(look_at_matrix(eye, target, Vec3::Y));
+
+        // The eye goes to the origin and the target straight ahead, down -z
+        assert_vec_eq(view.transform_point(eye), Vec3::ZERO);
+        let ahead = view.transform_point(target);
+        assert_vec_eq(ahead, Vec3::new(0.0, 0.0, -eye.distance(target)));
+
+        // Up stays up, and the view is a rigid motion
+        assert!(view.transform_vector(Vec3::Y).y > 0.0);
+        assert!(view.transform_vector(Vec3::Y).x.abs() < EPSILON);
+        assert!((view.determinant() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_planes() {
+        let projection = Mat4::perspective(FRAC_PI_2, 2.0, 0.5, 50.0);
+        let clip = |p: Vec3| (projection * p.extend(1.0)).perspective_divide();
+
+        assert_vec_eq(
+            clip(Vec3::new(0.0, 0.0, -0.5)),
+            Vec3::new(0.0, 0.0, -1.0),
+        );
+        assert_vec_eq(
+            clip(Vec3::new(0.0, 0.0, -50.0)),
+            Vec3::new(0.0, 0.0, 1.0),
+        );
+        // At 90 degrees the edges of the view are as far from the middle
+        // as they are deep, twice that across for an aspect ratio of 2
+        let depth = clip(Vec3::new(0.0, 0.0, -3.0)).z;
+        assert_vec_eq(
+            clip(Vec3::new(6.0, 3.0, -3.0)),
+            Vec3::new(1.0, 1.0, depth),
+        );
+    }
+
+    #[test]
+    fn orthographic_maps_the_box_to_the_cube() {
+        let projection =
+            Mat4(orthographic_matrix(-2.0, 4.0, -1.0, 3.0, 1.0, 10.0));
+
+        assert_vec_eq(
+            projection.transform_point(Vec3::new(-2.0, -1.0, -1.0)),
+            Vec3::new(-1.0, -1.0, -1.0),
+        );
+        assert_vec_eq(
+            projection.transform_point(Vec3::new(4.0, 3.0, -10.0)),
+            Vec3::ONE,
+        );
+    }
+}
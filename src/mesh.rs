@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::colour::Colour;
+use crate::error::SpatioError;
+use crate::picking::{pick_triangles, MeshHit, Pickable};
+use crate::ray::Ray;
+use crate::vector::Vec3;
+
+// Geometry on the CPU side, independent of any graphics API. Attributes are
+// packed the way they end up in vertex buffers: positions and normals as
+// x, y, z, uvs as u, v and colours as r, g, b, a. Attributes a mesh doesn't
+// have are left empty. Triangles are counter-clockwise seen from outside.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    pub positions: Vec<f32>,
+    pub normals:   Vec<f32>,
+    pub uvs:       Vec<f32>,
+    pub colours:   Vec<f32>,
+    pub indices:   Vec<u16>,
+}
+
+#[allow(dead_code)]
+impl Mesh {
+    pub fn new() -> Mesh {
+        Mesh::default()
+    }
+
+    // UV sphere centred on the origin, `precision` being the number of
+    // horizontal bands. The seam and the poles get duplicated vertices so each
+    // can have its own texture coordinates. Fails when the vertices don't fit
+    // in 16 bit indices, from a precision of 181.
+    pub fn sphere(radius: f32, precision: u16) -> Result<Mesh, SpatioError> {
+        let rings = precision.max(2) as usize;
+        let segments = rings * 2;
+        if (rings + 1) * (segments + 1) > u16::MAX as usize + 1 {
+            return Err(SpatioError::InvalidGeometry(format!(
+                "Sphere precision {} too high for 16 bit indices",
+                precision
+            )));
+        }
+
+        let mut mesh = Mesh::new();
+
+        for ring in 0..=rings {
+            let theta = PI * ring as f32 / rings as f32;
+
+            for segment in 0..=segments {
+                let phi = 2.0 * PI * segment as f32 / segments as f32;
+                let normal = Vec3::new(
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                    theta.sin() * phi.cos(),
+                );
+
+                mesh.push_vertex(
+                    normal * radius,
+                    normal,
+                    [
+                        segment as f32 / segments as f32,
+                        1.0 - ring as f32 / rings as f32,
+                    ],
+                );
+            }
+        }
+
+        let row = segments + 1;
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let top_left = (ring * row + segment) as u16;
+                let top_right = top_left + 1;
+                let bottom_left = top_left + row as u16;
+                let bottom_right = bottom_left + 1;
+
+                // Skip the triangles squashed into the poles
+                if ring != rings - 1 {
+                    mesh.indices.extend([top_left, bottom_left, bottom_right]);
+                }
+                if ring != 0 {
+                    mesh.indices.extend([top_left, bottom_right, top_right]);
+                }
+            }
+        }
+
+        return Ok(mesh);
+    }
+
+    // Axis aligned box between two opposite corners. Each face has its own
+    // four vertices so the normals stay flat.
+    pub fn cuboid(min: Vec3, max: Vec3) -> Mesh {
+        let (x0, y0, z0) = (min.x, min.y, min.z);
+        let (x1, y1, z1) = (max.x, max.y, max.z);
+
+        // Corners of each face from its bottom left, going counter-clockwise
+        let faces = [
+            (
+                Vec3::X,
+                [[x1, y0, z1], [x1, y0, z0], [x1, y1, z0], [x1, y1, z1]],
+            ),
+            (
+                -Vec3::X,
+                [[x0, y0, z0], [x0, y0, z1], [x0, y1, z1], [x0, y1, z0]],
+            ),
+            (
+                Vec3::Y,
+                [[x0, y1, z1], [x1, y1, z1], [x1, y1, z0], [x0, y1, z0]],
+            ),
+            (
+                -Vec3::Y,
+                [[x0, y0, z0], [x1, y0, z0], [x1, y0, z1], [x0, y0, z1]],
+            ),
+            (
+                Vec3::Z,
+                [[x0, y0, z1], [x1, y0, z1], [x1, y1, z1], [x0, y1, z1]],
+            ),
+            (
+                -Vec3::Z,
+                [[x1, y0, z0], [x0, y0, z0], [x0, y1, z0], [x1, y1, z0]],
+            ),
+        ];
+
+        let mut mesh = Mesh::new();
+
+        for (normal, corners) in faces {
+            let first = mesh.vertex_count() as u16;
+
+            for (corner, uv) in corners.into_iter().zip([
+                [0.0, 0.0],
+                [1.0, 0.0],
+                [1.0, 1.0],
+                [0.0, 1.0],
+            ]) {
+                mesh.push_vertex(Vec3::from(corner), normal, uv);
+            }
+
+            mesh.indices.extend([first, first + 1, first + 2]);
+            mesh.indices.extend([first, first + 2, first + 3]);
+        }
+
+        return mesh;
+    }
+
+    pub fn triangle(a: Vec3, b: Vec3, c: Vec3) -> Mesh {
+        let normal = (b - a).cross(c - a).normalize();
+
+        let mut mesh = Mesh::new();
+        mesh.push_vertex(a, normal, [0.0, 0.0]);
+        mesh.push_vertex(b, normal, [1.0, 0.0]);
+        mesh.push_vertex(c, normal, [0.0, 1.0]);
+        mesh.indices.extend([0, 1, 2]);
+
+        return mesh;
+    }
+
+    fn push_vertex(&mut self, position: Vec3, normal: Vec3, uv: [f32; 2]) {
+        self.positions.extend(position.to_array());
+        self.normals.extend(normal.to_array());
+        self.uvs.extend(uv);
+    }
+
+    // Gives every vertex the same colour, replacing any existing ones
+    pub fn with_colour(mut self, colour: &Colour) -> Mesh {
+        self.colours = [colour.r, colour.g, colour.b, colour.a]
+            .repeat(self.vertex_count());
+        return self;
+    }
+
+    // Colours each vertex from its position and normal, e.g. by height
+    // through a `Gradient`, or by face on a cuboid through its normal
+    pub fn with_vertex_colours(
+        mut self,
+        colour: impl Fn(Vec3, Vec3) -> Colour,
+    ) -> Mesh {
+        self.colours = (0..self.vertex_count())
+            .flat_map(|i| {
+                let normal = self.normal(i).unwrap_or(Vec3::ZERO);
+                let c = colour(self.position(i), normal);
+                [c.r, c.g, c.b, c.a]
+            })
+            .collect();
+        return self;
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len() / 3
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn position(&self, vertex: usize) -> Vec3 {
+        let i = vertex * 3;
+        Vec3::new(
+            self.positions[i],
+            self.positions[i + 1],
+            self.positions[i + 2],
+        )
+    }
+
+    pub fn normal(&self, vertex: usize) -> Option<Vec3> {
+        let i = vertex * 3;
+        let normal = self.normals.get(i..i + 3)?;
+        return Some(Vec3::new(normal[0], normal[1], normal[2]));
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = [u16; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]])
+    }
+
+    // Every edge of the triangles once, as pairs of indices. Vertices are
+    // matched by position, since seams and flat shaded faces duplicate them.
+    // With `hide_coplanar` the edges between two faces in the same plane are
+    // left out, so a cuboid gets its 12 edges and not the diagonals of its
+    // faces too.
+    pub fn edges(&self, hide_coplanar: bool) -> Vec<[u16; 2]> {
+        let key = |i: u16| {
+            let p = self.position(i as usize);
+            [p.x, p.y, p.z].map(|c| (c * 1e5).round() as i64)
+        };
+
+        let mut edges: Vec<([u16; 2], Vec<Vec3>)> = vec![];
+        let mut lookup = HashMap::new();
+
+        for [a, b, c] in self.triangles() {
+            let (pa, pb, pc) = (
+                self.position(a as usize),
+                self.position(b as usize),
+                self.position(c as usize),
+            );
+            let normal = (pb - pa).cross(pc - pa);
+            if normal.length_squared() == 0.0 {
+                continue;
+            }
+            let normal = normal.normalize();
+
+            for [i, j] in [[a, b], [b, c], [c, a]] {
+                let (ki, kj) = (key(i), key(j));
+                if ki == kj {
+                    continue;
+                }
+
+                let k = if ki < kj { (ki, kj) } else { (kj, ki) };
+                let edge = *lookup.entry(k).or_insert_with(|| {
+                    edges.push(([i, j], vec![]));
+                    edges.len() - 1
+                });
+                edges[edge].1.push(normal);
+            }
+        }
+
+        return edges
+            .into_iter()
+            .filter(|(_, normals)| {
+                let coplanar = normals.len() == 2 &&
+                    normals[0].dot(normals[1]) > 1.0 - 1e-4;
+                !(hide_coplanar && coplanar)
+            })
+            .map(|(edge, _)| edge)
+            .collect();
+    }
+
+    // Smallest axis aligned box containing every vertex, None when empty
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let mut points = (0..self.vertex_count()).map(|i| self.position(i));
+        let first = points.next()?;
+
+        return Some(points.fold((first, first), |(min, max), p| {
+            (
+                Vec3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Vec3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        }));
+    }
+}
+
+impl Pickable for Mesh {
+    fn pick(&self, ray: &Ray) -> Option<MeshHit> {
+        pick_triangles(ray, &self.positions, &self.indices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    // Indices in range, unit normals pointing away from `centre`, UVs in
+    // [0, 1] and triangles counter-clockwise seen from outside
+    fn check_closed(mesh: &Mesh, centre: Vec3) {
+        assert_eq!(mesh.normals.len(), mesh.positions.len());
+        assert_eq!(mesh.uvs.len(), mesh.vertex_count() * 2);
+        assert_eq!(mesh.indices.len() % 3, 0);
+        assert!(mesh
+            .indices
+            .iter()
+            .all(|&i| (i as usize) < mesh.vertex_count()));
+
+        for i in 0..mesh.vertex_count() {
+            let normal = mesh.normal(i).unwrap();
+            assert!((normal.length() - 1.0).abs() < EPSILON);
+            assert!(normal.dot(mesh.position(i) - centre) > 0.0);
+        }
+        assert!(mesh.uvs.iter().all(|uv| (0.0..=1.0).contains(uv)));
+
+        for [a, b, c] in mesh.triangles() {
+            let (pa, pb, pc) = (
+                mesh.position(a as usize),
+                mesh.position(b as usize),
+                mesh.position(c as usize),
+            );
+            let facing = (pb - pa).cross(pc - pa);
+            assert!(facing.dot((pa + pb + pc) / 3.0 - centre) > 0.0);
+        }
+    }
+
+    #[test]
+    fn sphere_counts() {
+        for precision in [2, 3, 10, 180] {
+            let mesh = Mesh::sphere(1.0, precision).unwrap();
+            let rings = precision as usize;
+            let segments = rings * 2;
+
+            assert_eq!(mesh.vertex_count(), (rings + 1) * (segments + 1));
+            // A quad per ring and segment, but a single triangle at the
+            // poles
+            assert_eq!(mesh.triangle_count(), 2 * segments * (rings - 1));
+        }
+    }
+
+    #[test]
+    fn sphere_precision_is_at_least_two() {
+        assert_eq!(Mesh::sphere(1.0, 0), Mesh::sphere(1.0, 2));
+    }
+
+    #[test]
+    fn sphere_too_precise_for_u16_indices() {
+        assert!(matches!(
+            Mesh::sphere(1.0, 181),
+            Err(SpatioError::InvalidGeometry(_))
+        ));
+    }
+
+    #[test]
+    fn sphere_geometry() {
+        let mesh = Mesh::sphere(2.5, 12).unwrap();
+        check_closed(&mesh, Vec3::ZERO);
+
+        for i in 0..mesh.vertex_count() {
+            assert!((mesh.position(i).length() - 2.5).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn cuboid_counts() {
+        let mesh = Mesh::cuboid(Vec3::ZERO, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(mesh.vertex_count(), 24);
+        assert_eq!(mesh.indices.len(), 36);
+    }
+
+    #[test]
+    fn cuboid_geometry() {
+        let (min, max) = (Vec3::new(-1.0, 0.0, 2.0), Vec3::new(3.0, 1.0, 4.0));
+        let mesh = Mesh::cuboid(min, max);
+        check_closed(&mesh, (min + max) / 2.0);
+
+        assert_eq!(mesh.bounds(), Some((min, max)));
+        assert_eq!(mesh.edges(true).len(), 12);
+        assert_eq!(mesh.edges(false).len(), 18);
+    }
+
+    #[test]
+    fn triangle_normal() {
+        let mesh = Mesh::triangle(Vec3::ZERO, Vec3::X, Vec3::Y);
+        assert_eq!(mesh.vertex_count(), 3);
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+        for i in 0..3 {
+            assert_eq!(mesh.normal(i), Some(Vec3::Z));
+        }
+    }
+}
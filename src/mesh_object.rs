@@ -0,0 +1,137 @@
+use std::rc::Rc;
+
+use crate::backend::Backend;
+use crate::camera::Camera;
+use crate::colour::Colour;
+use crate::error::SpatioError;
+use crate::gpu_mesh::GpuMesh;
+use crate::light::Light;
+use crate::material::Material;
+use crate::matrix::{Mat4, Matrix4F};
+use crate::mesh::Mesh;
+use crate::picking::{pick_transformed, MeshHit, Pickable};
+use crate::primitives::Draw;
+use crate::ray::Ray;
+use crate::render_mode::{draw_mesh, Edges, RenderMode};
+use crate::transform::Transform;
+use crate::vector::Vec3;
+
+// A mesh placed in the scene and drawn with a material in one of the render
+// modes. The CPU copy of the mesh is kept for picking and for working out
+// edges. `Ball`, `Box` and `Triangle` are each one of these plus what they
+// need for their exact ray intersection, and deref to it.
+pub struct MeshObject {
+    pub transform: Transform,
+    mesh:          Mesh,
+    gpu_mesh:      GpuMesh,
+    material:      Rc<Material>,
+    render_mode:   RenderMode,
+}
+
+#[allow(dead_code)]
+impl MeshObject {
+    pub fn new(
+        backend: &mut dyn Backend,
+        mesh: Mesh,
+        transform: Transform,
+        material: Rc<Material>,
+    ) -> Result<MeshObject, SpatioError> {
+        let gpu_mesh = GpuMesh::new(backend, &mesh)?;
+
+        return Ok(MeshObject {
+            transform,
+            mesh,
+            gpu_mesh,
+            material,
+            render_mode: RenderMode::Solid,
+        });
+    }
+
+    // In the object's local space
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    pub fn material(&self) -> &Rc<Material> {
+        &self.material
+    }
+
+    // Per-vertex colours from the mesh's local positions and normals. They
+    // multiply the material's colour, which is best left white.
+    pub fn set_vertex_colours(
+        &mut self,
+        backend: &mut dyn Backend,
+        colour: impl Fn(Vec3, Vec3) -> Colour,
+    ) -> Result<(), SpatioError> {
+        self.mesh = std::mem::take(&mut self.mesh).with_vertex_colours(colour);
+        self.gpu_mesh.set_colours(backend, &self.mesh.colours)?;
+
+        return Ok(());
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    // Uploads the edges the mode needs
+    pub fn set_render_mode(
+        &mut self,
+        backend: &mut dyn Backend,
+        mode: RenderMode,
+    ) -> Result<(), SpatioError> {
+        if let Some(edges) = mode.edges() {
+            let edges = self.mesh.edges(edges == Edges::HideCoplanar);
+            self.gpu_mesh.set_edges(backend, &edges)?;
+        }
+
+        self.render_mode = mode;
+        return Ok(());
+    }
+
+    pub fn delete(self, backend: &mut dyn Backend) {
+        self.gpu_mesh.delete(backend);
+    }
+}
+
+impl Draw for MeshObject {
+    fn draw(
+        &self,
+        backend: &mut dyn Backend,
+        camera: &Camera,
+        lights: &[Light],
+        parent_matrix: Option<Matrix4F>,
+    ) -> Result<(), SpatioError> {
+        let program = self.material.program();
+
+        self.material.apply(backend, lights)?;
+        self.gpu_mesh.bind(backend, program)?;
+        program.set_matrices(
+            backend,
+            camera,
+            parent_matrix,
+            &self.transform,
+        )?;
+
+        draw_mesh(backend, &self.gpu_mesh, &self.material, self.render_mode)?;
+
+        return Ok(());
+    }
+
+    fn is_transparent(&self) -> bool {
+        self.material.is_transparent()
+    }
+
+    fn centre(&self) -> Vec3 {
+        let (min, max) = self.mesh.bounds().unwrap_or((Vec3::ZERO, Vec3::ZERO));
+        return Mat4(self.transform.matrix())
+            .transform_point((min + max) / 2.0);
+    }
+}
+
+impl Pickable for MeshObject {
+    fn pick(&self, ray: &Ray) -> Option<MeshHit> {
+        pick_transformed(ray, &self.transform.matrix(), |ray| {
+            self.mesh.pick(ray)
+        })
+    }
+}
@@ -0,0 +1,237 @@
+use std::f32::consts::FRAC_PI_2;
+
+use crate::camera::Camera;
+use crate::vector::Vec3;
+
+// Turntable camera rotating around `target`. Input is accumulated as pending
+// deltas which `update` eases in over a few frames, giving the motion some
+// inertia. A damping of 1.0 applies input immediately.
+pub struct OrbitControls {
+    pub target:       Vec3,
+    pub yaw:          f32,
+    pub pitch:        f32,
+    pub distance:     f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub min_pitch:    f32,
+    pub max_pitch:    f32,
+    pub rotate_speed: f32,
+    pub zoom_speed:   f32,
+    pub damping:      f32,
+    yaw_delta:        f32,
+    pitch_delta:      f32,
+    zoom_delta:       f32,
+    pan_delta:        Vec3,
+}
+
+#[allow(dead_code)]
+impl OrbitControls {
+    pub fn new(target: Vec3, distance: f32) -> OrbitControls {
+        OrbitControls {
+            target,
+            yaw: 0.0,
+            pitch: 0.0,
+            distance,
+            min_distance: 0.1,
+            max_distance: 100.0,
+            // Stop just short of the poles, where the up vector degenerates
+            min_pitch: -FRAC_PI_2 + 0.01,
+            max_pitch: FRAC_PI_2 - 0.01,
+            rotate_speed: 0.005,
+            zoom_speed: 0.001,
+            damping: 0.2,
+            yaw_delta: 0.0,
+            pitch_delta: 0.0,
+            zoom_delta: 0.0,
+            pan_delta: Vec3::ZERO,
+        }
+    }
+
+    // Starts from wherever the camera currently is
+    pub fn from_camera(camera: &Camera) -> OrbitControls {
+        let offset = camera.eye - camera.target;
+        let distance = offset.length();
+
+        let mut controls = OrbitControls::new(camera.target, distance);
+        if distance > 0.0 {
+            controls.yaw = offset.x.atan2(offset.z);
+            controls.pitch = (offset.y / distance).clamp(-1.0, 1.0).asin();
+        }
+        return controls;
+    }
+
+    // Drag distances in pixels. Dragging right spins the scene right.
+    pub fn rotate(&mut self, dx: f32, dy: f32) {
+        self.yaw_delta -= dx * self.rotate_speed;
+        self.pitch_delta += dy * self.rotate_speed;
+    }
+
+    // Moves the target so the point under the cursor follows it
+    pub fn pan(&mut self, dx: f32, dy: f32, camera: &Camera) {
+        let (_, viewport_height) = camera.viewport();
+        let visible_height =
+            2.0 * self.distance * (camera.clamped_fov() / 2.0).tan();
+        let units_per_pixel = visible_height / viewport_height;
+
+        let forward = -self.offset().normalize();
+        let right = forward.cross(Vec3::Y).normalize();
+        let up = right.cross(forward);
+
+        self.pan_delta += (up * dy - right * dx) * units_per_pixel;
+    }
+
+    // Positive amounts (wheel scrolled down) move away from the target
+    pub fn zoom(&mut self, amount: f32) {
+        self.zoom_delta += amount * self.zoom_speed;
+    }
+
+    // Multiplies the distance directly, used by pinch gestures
+    pub fn zoom_by(&mut self, factor: f32) {
+        if factor > 0.0 {
+            self.zoom_delta += factor.ln();
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.yaw_delta = 0.0;
+        self.pitch_delta = 0.0;
+        self.zoom_delta = 0.0;
+        self.pan_delta = Vec3::ZERO;
+    }
+
+    fn offset(&self) -> Vec3 {
+        Vec3::new(
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.cos(),
+        ) * self.distance
+    }
+
+    // `dt` in seconds. Damping is defined per 60th of a second so the feel
+    // doesn't depend on the frame rate.
+    pub fn update(&mut self, dt: f32, camera: &mut Camera) {
+        let applied = if self.damping >= 1.0 {
+            1.0
+        } else {
+            1.0 - (1.0 - self.damping).powf(dt * 60.0)
+        };
+
+        self.yaw += self.yaw_delta * applied;
+        self.pitch += self.pitch_delta * applied;
+        self.distance *= (self.zoom_delta * applied).exp();
+        self.target += self.pan_delta * applied;
+
+        self.yaw_delta *= 1.0 - applied;
+        self.pitch_delta *= 1.0 - applied;
+        self.zoom_delta *= 1.0 - applied;
+        self.pan_delta *= 1.0 - applied;
+
+        self.pitch = self.pitch.clamp(self.min_pitch, self.max_pitch);
+        self.distance =
+            self.distance.clamp(self.min_distance, self.max_distance);
+
+        camera.target = self.target;
+        camera.eye = self.target + self.offset();
+        camera.up = Vec3::Y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn camera() -> Camera {
+        Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO)
+    }
+
+    #[test]
+    fn full_damping_applies_input_immediately() {
+        let mut camera = camera();
+        let mut controls = OrbitControls::from_camera(&camera);
+        controls.damping = 1.0;
+
+        controls.rotate(100.0, 0.0);
+        controls.zoom_by(2.0);
+        controls.update(0.0, &mut camera);
+
+        assert!((controls.yaw + 100.0 * controls.rotate_speed).abs() < EPSILON);
+        assert!((controls.distance - 10.0).abs() < EPSILON);
+        assert!((camera.eye.length() - 10.0).abs() < EPSILON);
+
+        // Nothing left to apply
+        let eye = camera.eye;
+        controls.update(1.0, &mut camera);
+        assert!(camera.eye.distance(eye) < EPSILON);
+    }
+
+    #[test]
+    fn easing_doesnt_depend_on_the_frame_rate() {
+        let (mut slow_camera, mut fast_camera) = (camera(), camera());
+        let mut slow = OrbitControls::from_camera(&slow_camera);
+        let mut fast = OrbitControls::from_camera(&fast_camera);
+
+        for controls in [&mut slow, &mut fast] {
+            controls.rotate(50.0, -30.0);
+            controls.zoom(200.0);
+        }
+        slow.update(1.0 / 30.0, &mut slow_camera);
+        fast.update(1.0 / 60.0, &mut fast_camera);
+        fast.update(1.0 / 60.0, &mut fast_camera);
+
+        assert!((slow.yaw - fast.yaw).abs() < EPSILON);
+        assert!((slow.pitch - fast.pitch).abs() < EPSILON);
+        assert!((slow.distance - fast.distance).abs() < EPSILON);
+        assert!(slow_camera.eye.distance(fast_camera.eye) < EPSILON);
+    }
+
+    #[test]
+    fn update_converges_on_the_input() {
+        let mut camera = camera();
+        let mut controls = OrbitControls::from_camera(&camera);
+
+        controls.rotate(-40.0, 20.0);
+        controls.pan(10.0, 0.0, &camera);
+        for _ in 0..600 {
+            controls.update(1.0 / 60.0, &mut camera);
+        }
+
+        assert!((controls.yaw - 40.0 * controls.rotate_speed).abs() < EPSILON);
+        assert!(
+            (controls.pitch - 20.0 * controls.rotate_speed).abs() < EPSILON
+        );
+        // Dragging right moves the scene right, so the target left
+        assert!(controls.target.x < 0.0);
+        assert_eq!(camera.target, controls.target);
+    }
+
+    #[test]
+    fn pitch_and_distance_are_clamped() {
+        let mut camera = camera();
+        let mut controls = OrbitControls::from_camera(&camera);
+        controls.damping = 1.0;
+
+        controls.rotate(0.0, 1e6);
+        controls.zoom_by(1e6);
+        controls.update(0.0, &mut camera);
+        assert_eq!(controls.pitch, controls.max_pitch);
+        assert_eq!(controls.distance, controls.max_distance);
+
+        controls.rotate(0.0, -1e7);
+        controls.zoom_by(1e-9);
+        controls.update(0.0, &mut camera);
+        assert_eq!(controls.pitch, controls.min_pitch);
+        assert_eq!(controls.distance, controls.min_distance);
+    }
+
+    #[test]
+    fn starts_where_the_camera_is() {
+        let mut camera = Camera::new(Vec3::new(1.0, 2.0, -3.0), Vec3::X);
+        let eye = camera.eye;
+
+        let mut controls = OrbitControls::from_camera(&camera);
+        controls.update(1.0 / 60.0, &mut camera);
+        assert!(camera.eye.distance(eye) < EPSILON);
+    }
+}
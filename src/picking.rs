@@ -0,0 +1,347 @@
+#[cfg(feature = "web")]
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[cfg(feature = "web")]
+use js_sys::Function;
+#[cfg(feature = "web")]
+use wasm_bindgen::prelude::*;
+
+use crate::matrix::{Mat4, Matrix4F, ID_MATRIX};
+use crate::ray::{intersect_triangle, Ray, RayHit};
+use crate::scene::Scene;
+use crate::vector::Vec3;
+
+// What is under the cursor. `face` is the index of the triangle that was hit
+// in the object's index buffer and `vertex` the index of the vertex of that
+// triangle closest to the hit point.
+#[cfg_attr(feature = "web", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickEvent {
+    pub object_id: u32,
+    pub face:      usize,
+    pub vertex:    usize,
+    pub distance:  f32,
+    pub x:         f32,
+    pub y:         f32,
+    pub z:         f32,
+}
+
+// Hit against an object's own geometry, in object space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshHit {
+    pub hit:    RayHit,
+    pub face:   usize,
+    pub vertex: usize,
+}
+
+// Like `intersect_transformed`, for mesh picking
+pub fn pick_transformed(
+    ray: &Ray,
+    model: &Matrix4F,
+    test: impl FnOnce(&Ray) -> Option<MeshHit>,
+) -> Option<MeshHit> {
+    let model = Mat4(*model);
+    let hit = test(&ray.transform(&model.inverse()?))?;
+    return Some(MeshHit {
+        hit: hit.hit.transform(&model),
+        ..hit
+    });
+}
+
+pub trait Pickable {
+    fn pick(&self, ray: &Ray) -> Option<MeshHit>;
+}
+
+struct PickTarget {
+    id:      u32,
+    object:  Rc<dyn Pickable>,
+    model:   Matrix4F,
+    enabled: bool,
+}
+
+#[derive(Default)]
+pub struct Picker {
+    targets: Vec<PickTarget>,
+    hovered: Option<(u32, usize, usize)>,
+}
+
+#[cfg(feature = "web")]
+thread_local! {
+    static ON_CLICK: RefCell<Option<Function>> = const { RefCell::new(None) };
+    static ON_HOVER: RefCell<Option<Function>> = const { RefCell::new(None) };
+}
+
+// Called with a `PickEvent` whenever an object is clicked
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_on_click(callback: Option<Function>) {
+    ON_CLICK.with(|on_click| *on_click.borrow_mut() = callback);
+}
+
+// Called with a `PickEvent` when the cursor moves onto a different object,
+// face or vertex, and with null when it leaves every object
+#[cfg(feature = "web")]
+#[wasm_bindgen]
+pub fn set_on_hover(callback: Option<Function>) {
+    ON_HOVER.with(|on_hover| *on_hover.borrow_mut() = callback);
+}
+
+#[cfg(feature = "web")]
+fn call_js(callback: &RefCell<Option<Function>>, argument: JsValue) {
+    if let Some(function) = callback.borrow().as_ref() {
+        // Exceptions thrown by the page's handler are not our problem
+        let _ = function.call1(&JsValue::NULL, &argument);
+    }
+}
+
+#[allow(dead_code)]
+impl Picker {
+    pub fn new() -> Picker {
+        Picker::default()
+    }
+
+    // Placed like the scene node with the same id, see `update_from_scene`
+    pub fn add(&mut self, id: u32, object: Rc<dyn Pickable>) {
+        self.targets.push(PickTarget {
+            id,
+            object,
+            model: ID_MATRIX,
+            enabled: true,
+        });
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        self.targets.retain(|target| target.id != id);
+    }
+
+    // Takes model matrices from the scene nodes sharing the targets' ids.
+    // Targets whose node is hidden, or missing, can't be picked.
+    pub fn update_from_scene(&mut self, scene: &Scene) {
+        for target in &mut self.targets {
+            target.enabled = false;
+        }
+
+        scene.root.visit(&mut |node| {
+            let Some(id) = node.id else {
+                return;
+            };
+            for target in self.targets.iter_mut().filter(|t| t.id == id) {
+                target.model = node.world_matrix();
+                target.enabled = true;
+            }
+        });
+    }
+
+    // Closest object along a world space ray
+    pub fn pick(&self, ray: &Ray) -> Option<PickEvent> {
+        let mut closest: Option<PickEvent> = None;
+
+        for target in self.targets.iter().filter(|t| t.enabled) {
+            let model = Mat4(target.model);
+            let Some(inverse) = model.inverse() else {
+                continue;
+            };
+
+            let Some(hit) = target.object.pick(&ray.transform(&inverse)) else {
+                continue;
+            };
+
+            if closest.is_some_and(|c| c.distance <= hit.hit.distance) {
+                continue;
+            }
+
+            let point = model.transform_point(hit.hit.point);
+            closest = Some(PickEvent {
+                object_id: target.id,
+                face:      hit.face,
+                vertex:    hit.vertex,
+                distance:  hit.hit.distance,
+                x:         point.x,
+                y:         point.y,
+                z:         point.z,
+            });
+        }
+
+        return closest;
+    }
+
+    // Whatever is now under the cursor, if that changed since the last call.
+    // `Some(None)` means the cursor left every object.
+    pub fn update_hover(&mut self, ray: &Ray) -> Option<Option<PickEvent>> {
+        let event = self.pick(ray);
+        let hovered = event.map(|e| (e.object_id, e.face, e.vertex));
+
+        if hovered == self.hovered {
+            return None;
+        }
+        self.hovered = hovered;
+
+        return Some(event);
+    }
+
+    #[cfg(feature = "web")]
+    pub fn click(&self, ray: &Ray) {
+        if let Some(event) = self.pick(ray) {
+            ON_CLICK.with(|on_click| call_js(on_click, event.into()));
+        }
+    }
+
+    #[cfg(feature = "web")]
+    pub fn hover(&mut self, ray: &Ray) {
+        let Some(event) = self.update_hover(ray) else {
+            return;
+        };
+
+        let argument = match event {
+            Some(event) => event.into(),
+            None => JsValue::NULL,
+        };
+        ON_HOVER.with(|on_hover| call_js(on_hover, argument));
+    }
+}
+
+// Brute force test of every triangle of an indexed mesh. Positions are packed
+// x, y, z like the vertex buffers.
+pub fn pick_triangles(
+    ray: &Ray,
+    positions: &[f32],
+    indices: &[u16],
+) -> Option<MeshHit> {
+    let vertex = |index: u16| {
+        let i = index as usize * 3;
+        Vec3::new(positions[i], positions[i + 1], positions[i + 2])
+    };
+
+    let mut closest: Option<MeshHit> = None;
+
+    for (face, triangle) in indices.chunks_exact(3).enumerate() {
+        let corners = [
+            vertex(triangle[0]),
+            vertex(triangle[1]),
+            vertex(triangle[2]),
+        ];
+
+        let Some(hit) =
+            intersect_triangle(ray, corners[0], corners[1], corners[2])
+        else {
+            continue;
+        };
+
+        if closest.is_some_and(|c| c.hit.distance <= hit.distance) {
+            continue;
+        }
+
+        let nearest = (0..3)
+            .min_by(|&a, &b| {
+                let a = corners[a].distance(hit.point);
+                let b = corners[b].distance(hit.point);
+                a.total_cmp(&b)
+            })
+            .unwrap();
+
+        closest = Some(MeshHit {
+            hit,
+            face,
+            vertex: triangle[nearest] as usize,
+        });
+    }
+
+    return closest;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+    use crate::scene::SceneNode;
+
+    const EPSILON: f32 = 1e-5;
+
+    // Unit cubes, the second 5 units further along -z than the first
+    fn scene_and_picker() -> (Scene, Picker) {
+        let cube = Rc::new(Mesh::cuboid(-Vec3::ONE * 0.5, Vec3::ONE * 0.5));
+
+        let mut scene = Scene::new();
+        scene.add(SceneNode::new().with_id(1));
+        scene.add(
+            SceneNode::new()
+                .with_id(2)
+                .with_transform(Mat4::translation(-Vec3::Z * 5.0).0),
+        );
+        scene.update_world_matrices();
+
+        let mut picker = Picker::new();
+        picker.add(2, cube.clone());
+        picker.add(1, cube);
+        picker.update_from_scene(&scene);
+
+        return (scene, picker);
+    }
+
+    fn forward_ray(x: f32, y: f32) -> Ray {
+        Ray::new(Vec3::new(x, y, 10.0), -Vec3::Z)
+    }
+
+    #[test]
+    fn nearest_hit_wins() {
+        let (_, picker) = scene_and_picker();
+
+        let event = picker.pick(&forward_ray(0.1, 0.2)).unwrap();
+        assert_eq!(event.object_id, 1);
+        assert!((event.distance - 9.5).abs() < EPSILON);
+        assert!((event.z - 0.5).abs() < EPSILON);
+
+        assert_eq!(picker.pick(&forward_ray(2.0, 0.0)), None);
+    }
+
+    #[test]
+    fn targets_follow_their_nodes() {
+        let (mut scene, mut picker) = scene_and_picker();
+
+        // Moved aside, and scaled up so it reaches 1.5 units from its centre
+        let node = scene.find_mut(1).unwrap();
+        node.transform = (Mat4::translation(Vec3::new(3.0, 0.0, 0.0)) *
+            Mat4::scale(Vec3::ONE * 3.0))
+        .0;
+        scene.update_world_matrices();
+        picker.update_from_scene(&scene);
+
+        let event = picker.pick(&forward_ray(0.1, 0.2)).unwrap();
+        assert_eq!(event.object_id, 2);
+        assert!((event.distance - 14.5).abs() < EPSILON);
+
+        let event = picker.pick(&forward_ray(4.0, 1.0)).unwrap();
+        assert_eq!(event.object_id, 1);
+        assert!((event.distance - 8.5).abs() < EPSILON);
+        assert!((event.x - 4.0).abs() < EPSILON);
+        assert!((event.z - 1.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn hidden_and_missing_nodes_cant_be_picked() {
+        let (mut scene, mut picker) = scene_and_picker();
+
+        scene.find_mut(1).unwrap().visible = false;
+        picker.update_from_scene(&scene);
+        let event = picker.pick(&forward_ray(0.1, 0.2)).unwrap();
+        assert_eq!(event.object_id, 2);
+
+        picker.add(3, Rc::new(Mesh::cuboid(Vec3::ZERO, Vec3::ONE * 20.0)));
+        picker.update_from_scene(&scene);
+        assert_eq!(picker.pick(&forward_ray(0.1, 0.2)).unwrap().object_id, 2);
+
+        picker.remove(2);
+        assert_eq!(picker.pick(&forward_ray(0.1, 0.2)), None);
+    }
+
+    #[test]
+    fn hover_reports_changes_only() {
+        let (_, mut picker) = scene_and_picker();
+
+        let first = picker.update_hover(&forward_ray(0.1, 0.2));
+        assert_eq!(first.unwrap().unwrap().object_id, 1);
+        assert_eq!(picker.update_hover(&forward_ray(0.1, 0.2)), None);
+        assert_eq!(picker.update_hover(&forward_ray(2.0, 0.0)), Some(None));
+    }
+}
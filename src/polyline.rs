@@ -0,0 +1,690 @@
+use std::rc::Rc;
+
+use crate::backend::{Backend, BufferData, BufferHandle, DrawMode, Uniform};
+use crate::camera::Camera;
+use crate::error::SpatioError;
+use crate::light::Light;
+use crate::material::Material;
+use crate::matrix::{Mat4, Matrix4F};
+use crate::primitives::Draw;
+use crate::transform::Transform;
+use crate::vector::{Vec3, Vertex};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineWidth {
+    // In CSS pixels, so the same on screen at any distance and on any display
+    Pixels(f32),
+    // In the polyline's units, so it gets thinner further away
+    World(f32),
+}
+
+impl LineWidth {
+    // Half the width in pixels where the line is `w` deep in clip space,
+    // `focal` being the projection's scale of y, its [1][1] element. Works
+    // for both projections.
+    pub fn half_pixels(self, focal: f32, viewport_height: f32, w: f32) -> f32 {
+        match self {
+            LineWidth::Pixels(width) => width * 0.5,
+            LineWidth::World(width) => {
+                width * 0.5 * focal * viewport_height * 0.5 / w
+            }
+        }
+    }
+}
+
+// How the two ends of an open polyline are finished
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    // Stops at the end point
+    Butt,
+    // Carries on half the width past the end point
+    Square,
+    Round,
+}
+
+// How consecutive segments meet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineStyle {
+    pub width:       LineWidth,
+    pub cap:         LineCap,
+    pub join:        LineJoin,
+    // Longest a miter can get, in half widths, before the join is rounded
+    // instead
+    pub miter_limit: f32,
+    // Lengths of the dashes and of the gaps between them, in the polyline's
+    // units. Solid when None.
+    pub dash:        Option<[f32; 2]>,
+}
+
+#[allow(dead_code)]
+impl LineStyle {
+    pub fn new(width: LineWidth) -> LineStyle {
+        LineStyle {
+            width,
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            miter_limit: 4.0,
+            dash: None,
+        }
+    }
+
+    pub fn with_cap(mut self, cap: LineCap) -> LineStyle {
+        self.cap = cap;
+        return self;
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> LineStyle {
+        self.join = join;
+        return self;
+    }
+
+    pub fn with_dash(mut self, dash: f32, gap: f32) -> LineStyle {
+        self.dash = Some([dash, gap]);
+        return self;
+    }
+
+    // The values of the polyline program's style uniforms, with
+    // `pixel_ratio` drawing buffer pixels per CSS pixel
+    pub fn uniforms(&self, pixel_ratio: f32) -> [(&'static str, Uniform); 6] {
+        let (width, world) = match self.width {
+            LineWidth::Pixels(width) => (width * pixel_ratio, false),
+            LineWidth::World(width) => (width, true),
+        };
+
+        return [
+            ("u_line_width", Uniform::Float(width)),
+            ("u_world_width", Uniform::Int(world as i32)),
+            ("u_cap", Uniform::Int(self.cap as i32)),
+            ("u_join", Uniform::Int(self.join as i32)),
+            ("u_miter_limit", Uniform::Float(self.miter_limit)),
+            ("u_dash", Uniform::Vec2(self.dash.unwrap_or([0.0, 0.0]))),
+        ];
+    }
+
+    // Back from the values of `uniforms`, None when one is missing or of
+    // another type. Widths in pixels are then drawing buffer pixels.
+    pub fn from_uniforms(
+        uniform: impl Fn(&str) -> Option<Uniform>,
+    ) -> Option<LineStyle> {
+        let int = |name| match uniform(name)? {
+            Uniform::Int(value) => Some(value),
+            _ => None,
+        };
+        let float = |name| match uniform(name)? {
+            Uniform::Float(value) => Some(value),
+            _ => None,
+        };
+        let Some(Uniform::Vec2(dash)) = uniform("u_dash") else {
+            return None;
+        };
+
+        let width = float("u_line_width")?;
+        return Some(LineStyle {
+            width:       match int("u_world_width")? {
+                0 => LineWidth::Pixels(width),
+                _ => LineWidth::World(width),
+            },
+            cap:         match int("u_cap")? {
+                0 => LineCap::Butt,
+                1 => LineCap::Square,
+                _ => LineCap::Round,
+            },
+            join:        match int("u_join")? {
+                0 => LineJoin::Miter,
+                _ => LineJoin::Round,
+            },
+            miter_limit: float("u_miter_limit")?,
+            dash:        (dash[1] > 0.0).then_some(dash),
+        });
+    }
+}
+
+// What the polyline shaders work out on screen, for backends that emulate
+// them. Everything is in pixels, x right and y up.
+
+// How the end of a segment is finished. Open ends carry on into the next
+// segment, along the miter of the join.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentEnd {
+    Butt,
+    Square,
+    Round,
+    Open,
+}
+
+impl From<LineCap> for SegmentEnd {
+    fn from(cap: LineCap) -> SegmentEnd {
+        match cap {
+            LineCap::Butt => SegmentEnd::Butt,
+            LineCap::Square => SegmentEnd::Square,
+            LineCap::Round => SegmentEnd::Round,
+        }
+    }
+}
+
+// Where the corner on `side` (-1 or 1) of the end at `point` of a segment
+// going `direction` goes from that end, and how the end is finished.
+// `beyond` is the point past the end, the end itself when there's none. The
+// corner is a pixel wider than the line, room for the anti-aliased edge.
+pub fn finish_end(
+    style: &LineStyle,
+    point: Vec3,
+    beyond: Vec3,
+    at_end: bool,
+    direction: Vec3,
+    half_width: f32,
+    side: f32,
+) -> (SegmentEnd, Vec3) {
+    let normal = Vec3::new(-direction.y, direction.x, 0.0);
+    let outward = if at_end { direction } else { -direction };
+    let spread = half_width + 1.0;
+    let turn = beyond - point;
+
+    if turn.length() > 0.001 {
+        let incoming = if at_end {
+            direction
+        } else {
+            (-turn).normalize()
+        };
+        let outgoing = if at_end { turn.normalize() } else { direction };
+        let tangent = incoming + outgoing;
+
+        if tangent.length() > 0.001 {
+            let tangent = tangent.normalize();
+            let ratio = 1.0 / tangent.dot(direction);
+            if ratio <= style.miter_limit {
+                let miter = Vec3::new(-tangent.y, tangent.x, 0.0);
+                let end = match style.join {
+                    LineJoin::Miter => SegmentEnd::Open,
+                    LineJoin::Round => SegmentEnd::Round,
+                };
+                return (end, miter * (ratio * spread * side));
+            }
+        }
+
+        return (
+            SegmentEnd::Round,
+            normal * (spread * side) + outward * spread,
+        );
+    }
+
+    let end = SegmentEnd::from(style.cap);
+    let extension = if end == SegmentEnd::Butt { 1.0 } else { spread };
+    return (end, normal * (spread * side) + outward * extension);
+}
+
+// How much of the pixel `along` a segment `length` long from its start and
+// `across` it the segment covers, from 0 to 1. The half width goes from the
+// first of `half_widths` at the start to the second at the end.
+pub fn coverage(
+    along: f32,
+    across: f32,
+    length: f32,
+    half_widths: [f32; 2],
+    ends: [SegmentEnd; 2],
+) -> f32 {
+    let [start_width, end_width] = half_widths;
+    let t = (along / length.max(0.001)).clamp(0.0, 1.0);
+    let half_width = start_width + (end_width - start_width) * t;
+
+    let (past, end) = if along < 0.0 {
+        (-along, ends[0])
+    } else if along > length {
+        (along - length, ends[1])
+    } else {
+        (0.0, SegmentEnd::Open)
+    };
+
+    let straight = across.abs() - half_width;
+    let outside = match end {
+        SegmentEnd::Butt => straight.max(past),
+        SegmentEnd::Square => straight.max(past - half_width),
+        SegmentEnd::Round => {
+            (past * past + across * across).sqrt() - half_width
+        }
+        SegmentEnd::Open => straight,
+    };
+
+    return (0.5 - outside).clamp(0.0, 1.0);
+}
+
+// Whether `distance` along the line, in its units, falls between two dashes
+pub fn in_dash_gap(distance: f32, dash: Option<[f32; 2]>) -> bool {
+    match dash {
+        Some([dash, gap]) if gap > 0.0 => {
+            distance.rem_euclid(dash + gap) > dash
+        }
+        _ => false,
+    }
+}
+
+// Attributes of the polyline program, with their number of components
+const ATTRIBUTES: [(&str, u32); 6] = [
+    ("position", 3),
+    ("other", 3),
+    ("beyond", 3),
+    ("beyond_other", 3),
+    ("corner", 2),
+    ("line_distance", 1),
+];
+
+// A thick, anti-aliased line through any number of points, drawn with the
+// polyline program (see `Program::polyline`). Each segment is a quad worked
+// out on the GPU, so the line keeps its width in pixels as the camera moves.
+// Always blended, for the anti-aliased edges.
+pub struct Polyline {
+    pub transform: Transform,
+    pub style:     LineStyle,
+    // One per attribute, in the order of `ATTRIBUTES`
+    buffers:       Vec<BufferHandle>,
+    vertex_count:  usize,
+    centre:        Vec3,
+    material:      Rc<Material>,
+}
+
+// The vertices of the segments' quads, as the values of each attribute.
+// `closed` joins the last point back to the first.
+fn segments(points: &[Vec3], closed: bool) -> [Vec<f32>; 6] {
+    let mut attributes: [Vec<f32>; 6] = Default::default();
+    let count = points.len();
+    // Two points make the same segment both ways
+    let closed = closed && count > 2;
+    let segment_count = match count {
+        0 | 1 => 0,
+        _ if closed => count,
+        _ => count - 1,
+    };
+
+    // Past the ends of an open polyline is the end itself, which the shader
+    // takes for no neighbour
+    let point = |i: isize| -> Vec3 {
+        if closed {
+            return points[i.rem_euclid(count as isize) as usize];
+        }
+        return points[i.clamp(0, count as isize - 1) as usize];
+    };
+
+    let mut distance = 0.0;
+    for i in 0..segment_count as isize {
+        let (start, end) = (point(i), point(i + 1));
+        let (before, after) = (point(i - 1), point(i + 2));
+        let length = start.distance(end);
+
+        // Two triangles, corners as (at end, side)
+        for (at_end, side) in
+            [(0, -1), (0, 1), (1, -1), (1, -1), (0, 1), (1, 1)]
+        {
+            let (own, other, beyond, beyond_other, own_distance) =
+                if at_end == 1 {
+                    (end, start, after, before, distance + length)
+                } else {
+                    (start, end, before, after, distance)
+                };
+
+            attributes[0].extend(own.to_array());
+            attributes[1].extend(other.to_array());
+            attributes[2].extend(beyond.to_array());
+            attributes[3].extend(beyond_other.to_array());
+            attributes[4].extend([side as f32, at_end as f32]);
+            attributes[5].push(own_distance);
+        }
+        distance += length;
+    }
+
+    return attributes;
+}
+
+#[allow(dead_code)]
+impl Polyline {
+    // Fails when the material's program isn't a polyline one
+    pub fn new(
+        backend: &mut dyn Backend,
+        points: &[Vertex],
+        closed: bool,
+        style: LineStyle,
+        material: Rc<Material>,
+    ) -> Result<Polyline, SpatioError> {
+        material.program().require_uniforms(&["u_line_width"])?;
+
+        let mut polyline = Polyline {
+            transform: Transform::IDENTITY,
+            style,
+            buffers: vec![],
+            vertex_count: 0,
+            centre: Vec3::ZERO,
+            material,
+        };
+        polyline.set_points(backend, points, closed)?;
+
+        return Ok(polyline);
+    }
+
+    // Replaces the points, e.g. as a trajectory grows. Repeated points are
+    // dropped.
+    pub fn set_points(
+        &mut self,
+        backend: &mut dyn Backend,
+        points: &[Vertex],
+        closed: bool,
+    ) -> Result<(), SpatioError> {
+        let mut unique: Vec<Vec3> = Vec::with_capacity(points.len());
+        for point in points.iter().map(|&point| Vec3::from(point)) {
+            if unique.last() != Some(&point) {
+                unique.push(point);
+            }
+        }
+        if closed && unique.len() > 1 && unique.first() == unique.last() {
+            unique.pop();
+        }
+
+        let attributes = segments(&unique, closed);
+        let mut buffers = Vec::with_capacity(attributes.len());
+        for data in &attributes {
+            match backend.create_buffer(BufferData::Vertices(data)) {
+                Ok(buffer) => buffers.push(buffer),
+                Err(error) => {
+                    // Keeps the old points, without leaking the new buffers
+                    for buffer in buffers {
+                        backend.delete_buffer(buffer);
+                    }
+                    return Err(error);
+                }
+            }
+        }
+
+        self.delete_buffers(backend);
+        self.buffers = buffers;
+        self.vertex_count = attributes[5].len();
+        self.centre = match unique.len() {
+            0 => Vec3::ZERO,
+            n => unique.iter().fold(Vec3::ZERO, |sum, &p| sum + p) / n as f32,
+        };
+
+        return Ok(());
+    }
+
+    fn delete_buffers(&mut self, backend: &mut dyn Backend) {
+        for buffer in self.buffers.drain(..) {
+            backend.delete_buffer(buffer);
+        }
+    }
+
+    pub fn delete(mut self, backend: &mut dyn Backend) {
+        self.delete_buffers(backend);
+    }
+}
+
+impl Draw for Polyline {
+    fn draw(
+        &self,
+        backend: &mut dyn Backend,
+        camera: &Camera,
+        lights: &[Light],
+        parent_matrix: Option<Matrix4F>,
+    ) -> Result<(), SpatioError> {
+        if self.vertex_count == 0 {
+            return Ok(());
+        }
+        let program = self.material.program();
+
+        self.material.apply(backend, lights)?;
+        backend.set_blending(true);
+        // Segments overlap at the joins. Without depth writes the second one
+        // isn't hidden by the first, but a translucent colour is blended
+        // twice there.
+        backend.set_depth_write(false);
+
+        for ((name, size), buffer) in ATTRIBUTES.iter().zip(&self.buffers) {
+            backend.bind_attribute(name, *buffer, *size);
+        }
+        program.set_matrices(
+            backend,
+            camera,
+            parent_matrix,
+            &self.transform,
+        )?;
+
+        for (name, value) in self.style.uniforms(camera.pixel_ratio()) {
+            program.set_uniform(backend, name, value)?;
+        }
+
+        backend.draw(DrawMode::Triangles, None, self.vertex_count);
+        backend.set_depth_write(true);
+
+        return Ok(());
+    }
+
+    // The anti-aliased edges are blended
+    fn is_transparent(&self) -> bool {
+        true
+    }
+
+    fn centre(&self) -> Vec3 {
+        Mat4(self.transform.matrix()).transform_point(self.centre)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        assert!(a.distance(b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    fn style(cap: LineCap, join: LineJoin) -> LineStyle {
+        LineStyle::new(LineWidth::Pixels(4.0))
+            .with_cap(cap)
+            .with_join(join)
+    }
+
+    #[test]
+    fn uniforms_round_trip() {
+        let styles = [
+            LineStyle::new(LineWidth::Pixels(3.0)),
+            LineStyle::new(LineWidth::World(0.2))
+                .with_cap(LineCap::Square)
+                .with_join(LineJoin::Round)
+                .with_dash(0.5, 0.25),
+            style(LineCap::Round, LineJoin::Miter),
+        ];
+
+        for style in styles {
+            let uniforms = style.uniforms(1.0);
+            let uniform = |name: &str| {
+                uniforms.iter().find(|(n, _)| *n == name).map(|(_, u)| *u)
+            };
+            assert_eq!(LineStyle::from_uniforms(uniform), Some(style));
+        }
+        assert_eq!(LineStyle::from_uniforms(|_| None), None);
+    }
+
+    #[test]
+    fn pixel_widths_scale_with_pixel_ratio() {
+        let width = |style: LineStyle| style.uniforms(2.0)[0].1;
+
+        let pixels = LineStyle::new(LineWidth::Pixels(3.0));
+        assert_eq!(width(pixels), Uniform::Float(6.0));
+        let world = LineStyle::new(LineWidth::World(0.5));
+        assert_eq!(width(world), Uniform::Float(0.5));
+    }
+
+    #[test]
+    fn half_width_in_pixels() {
+        assert_eq!(LineWidth::Pixels(6.0).half_pixels(2.0, 100.0, 5.0), 3.0);
+        // A unit wide line 5 deep, with 100 pixels for 1 / focal units
+        assert_eq!(LineWidth::World(1.0).half_pixels(2.0, 100.0, 5.0), 10.0);
+    }
+
+    #[test]
+    fn straight_through_is_open() {
+        let style = style(LineCap::Butt, LineJoin::Miter);
+        let (end, offset) = finish_end(
+            &style,
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(20.0, 0.0, 0.0),
+            true,
+            Vec3::X,
+            2.0,
+            1.0,
+        );
+
+        assert_eq!(end, SegmentEnd::Open);
+        // Straight across, a pixel wider than the line
+        assert_vec_eq(offset, Vec3::new(0.0, 3.0, 0.0));
+    }
+
+    #[test]
+    fn right_angle_miter() {
+        let style = style(LineCap::Butt, LineJoin::Miter);
+        let point = Vec3::new(10.0, 0.0, 0.0);
+        let beyond = Vec3::new(10.0, 10.0, 0.0);
+
+        // Turning left, the left corner is on the inside of the join and the
+        // right one on the outside, both sqrt(2) times the spread away
+        let (end, inner) =
+            finish_end(&style, point, beyond, true, Vec3::X, 2.0, 1.0);
+        assert_eq!(end, SegmentEnd::Open);
+        assert_vec_eq(inner, Vec3::new(-3.0, 3.0, 0.0));
+
+        let (_, outer) =
+            finish_end(&style, point, beyond, true, Vec3::X, 2.0, -1.0);
+        assert_vec_eq(outer, Vec3::new(3.0, -3.0, 0.0));
+
+        // The next segment starts from the same corners
+        let (_, next) =
+            finish_end(&style, point, Vec3::ZERO, false, Vec3::Y, 2.0, -1.0);
+        assert_vec_eq(next, outer);
+    }
+
+    #[test]
+    fn round_join() {
+        let style = style(LineCap::Butt, LineJoin::Round);
+        let (end, _) = finish_end(
+            &style,
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(10.0, 10.0, 0.0),
+            true,
+            Vec3::X,
+            2.0,
+            1.0,
+        );
+        assert_eq!(end, SegmentEnd::Round);
+    }
+
+    #[test]
+    fn sharp_turn_past_miter_limit_is_round() {
+        let style = style(LineCap::Butt, LineJoin::Miter);
+        // Nearly doubling back, the miter would be very long
+        let (end, offset) = finish_end(
+            &style,
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            true,
+            Vec3::X,
+            2.0,
+            1.0,
+        );
+
+        assert_eq!(end, SegmentEnd::Round);
+        assert_vec_eq(offset, Vec3::new(3.0, 3.0, 0.0));
+    }
+
+    #[test]
+    fn caps_at_open_ends() {
+        let point = Vec3::new(10.0, 0.0, 0.0);
+        for (cap, extension) in [
+            (LineCap::Butt, 1.0),
+            (LineCap::Square, 3.0),
+            (LineCap::Round, 3.0),
+        ] {
+            let style = style(cap, LineJoin::Miter);
+            let (end, offset) =
+                finish_end(&style, point, point, true, Vec3::X, 2.0, -1.0);
+            assert_eq!(end, SegmentEnd::from(cap));
+            assert_vec_eq(offset, Vec3::new(extension, -3.0, 0.0));
+
+            // Backwards at the start
+            let (_, offset) =
+                finish_end(&style, point, point, false, Vec3::X, 2.0, -1.0);
+            assert_vec_eq(offset, Vec3::new(-extension, -3.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn coverage_across() {
+        let ends = [SegmentEnd::Open, SegmentEnd::Open];
+        let covered = |across| coverage(5.0, across, 10.0, [2.0, 2.0], ends);
+
+        assert_eq!(covered(0.0), 1.0);
+        assert_eq!(covered(-1.5), 1.0);
+        assert_eq!(covered(2.0), 0.5);
+        assert_eq!(covered(-2.25), 0.25);
+        assert_eq!(covered(3.0), 0.0);
+
+        // Tapering from 1 to 3 pixels, 2 in the middle
+        assert_eq!(coverage(5.0, 2.0, 10.0, [1.0, 3.0], ends), 0.5);
+    }
+
+    #[test]
+    fn coverage_past_the_ends() {
+        let covered = |end, along, across| {
+            coverage(along, across, 10.0, [2.0, 2.0], [end, end])
+        };
+
+        // Butt ends stop at the end point
+        assert_eq!(covered(SegmentEnd::Butt, -0.25, 0.0), 0.25);
+        assert_eq!(covered(SegmentEnd::Butt, 11.0, 0.0), 0.0);
+        // Square ones half the width further
+        assert_eq!(covered(SegmentEnd::Square, 11.5, 1.0), 1.0);
+        assert_eq!(covered(SegmentEnd::Square, -2.0, 0.0), 0.5);
+        // Round ones in a half disc
+        assert_eq!(covered(SegmentEnd::Round, 11.0, 1.0), 1.0);
+        assert_eq!(covered(SegmentEnd::Round, 12.0, 1.5), 0.0);
+        assert_eq!(covered(SegmentEnd::Round, -2.0, 0.0), 0.5);
+    }
+
+    #[test]
+    fn dash_gaps() {
+        assert!(!in_dash_gap(5.0, None));
+        assert!(!in_dash_gap(5.0, Some([1.0, 0.0])));
+
+        let dash = Some([2.0, 1.0]);
+        assert!(!in_dash_gap(0.5, dash));
+        assert!(in_dash_gap(2.5, dash));
+        assert!(!in_dash_gap(3.5, dash));
+        assert!(in_dash_gap(-0.5, dash));
+    }
+
+    #[test]
+    fn segment_vertices() {
+        let square = [Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::Y];
+
+        let open = segments(&square, false);
+        assert_eq!(open[5].len(), 3 * 6);
+        assert_eq!(open[0].len(), 3 * 6 * 3);
+        assert_eq!(open[4].len(), 3 * 6 * 2);
+        // Distances run along the line, up to its length at the very end
+        assert_eq!(open[5].last(), Some(&3.0));
+        // Past the first point is the point itself
+        assert_eq!(&open[2][..3], &[0.0, 0.0, 0.0]);
+
+        let closed = segments(&square, true);
+        assert_eq!(closed[5].len(), 4 * 6);
+        assert_eq!(closed[5].last(), Some(&4.0));
+        // Past the first point is the last one
+        assert_eq!(&closed[2][..3], &[0.0, 1.0, 0.0]);
+
+        assert!(segments(&square[..1], false).iter().all(Vec::is_empty));
+        // Two points make one segment, closed or not
+        assert_eq!(segments(&square[..2], true)[5].len(), 6);
+    }
+}
@@ -1,142 +1,104 @@
-use web_sys::{
-    WebGl2RenderingContext,
-    WebGlBuffer,
-    WebGlProgram,
-    WebGlUniformLocation,
-};
-
-use crate::colour::Colour;
-use crate::matrix::Matrix4F;
-use crate::ID_MATRIX;
-
+use std::rc::Rc;
+
+use crate::backend::Backend;
+use crate::camera::Camera;
+use crate::error::SpatioError;
+use crate::gpu_mesh::GpuMesh;
+use crate::light::Light;
+use crate::material::Material;
+use crate::matrix::{Mat4, Matrix4F};
+use crate::mesh::Mesh;
+use crate::transform::Transform;
+use crate::vector::{Vec3, Vertex};
+
+// `parent_matrix` is the world matrix of whatever the object is attached to,
+// e.g. its scene node. The object's own transform is applied on top of it.
+// `lights` are the scene's, ignored by unlit materials.
 pub trait Draw {
     fn draw(
         &self,
-        context: &WebGl2RenderingContext,
-        uniform_matrix: Option<Matrix4F>,
-    ) -> Result<(), String>;
-}
-
-#[derive(Clone, Copy)]
-#[allow(dead_code)]
-pub struct Vertex {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-}
+        backend: &mut dyn Backend,
+        camera: &Camera,
+        lights: &[Light],
+        parent_matrix: Option<Matrix4F>,
+    ) -> Result<(), SpatioError>;
+
+    // Whether it's blended over what's behind it, which puts it in the
+    // scene's transparent pass
+    fn is_transparent(&self) -> bool {
+        false
+    }
 
-#[allow(dead_code)]
-impl Vertex {
-    pub fn new(x: f32, y: f32, z: f32) -> Vertex {
-        Vertex {
-            x,
-            y,
-            z,
-        }
+    // Where the transparent pass sorts it by distance from, in the space
+    // `parent_matrix` maps from
+    fn centre(&self) -> Vec3 {
+        Vec3::ZERO
     }
 }
 
-
-pub struct Line<'a> {
-    position_buffer:               WebGlBuffer,
-    colour:                        Colour,
-    position_attribute_location:   i32,
-    colour_uniform_location:       WebGlUniformLocation,
-    model_matrix_uniform_location: WebGlUniformLocation,
-    program:                       &'a WebGlProgram,
+// A single segment drawn as GL lines, which are one pixel wide whatever the
+// platform. `Polyline` draws thick ones. It's a two vertex mesh with the
+// segment as its only edge, so it binds its attributes like any other mesh.
+pub struct Line {
+    pub transform: Transform,
+    gpu_mesh:      GpuMesh,
+    midpoint:      Vec3,
+    material:      Rc<Material>,
 }
 
 #[allow(dead_code)]
-impl<'a> Line<'a> {
+impl Line {
     pub fn new(
-        context: &WebGl2RenderingContext,
+        backend: &mut dyn Backend,
         a: Vertex,
         b: Vertex,
-        colour: Colour,
-        program: &'a WebGlProgram,
-    ) -> Line<'a> {
-        let vertices = [a.x, a.y, a.z, b.x, b.y, b.z];
-
-        let buffer = context.create_buffer().unwrap();
-
-        context
-            .bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
-
-        unsafe {
-            let positions_array_buf_view =
-                js_sys::Float32Array::view(&vertices);
-
-            context.buffer_data_with_array_buffer_view(
-                WebGl2RenderingContext::ARRAY_BUFFER,
-                &positions_array_buf_view,
-                WebGl2RenderingContext::STATIC_DRAW,
-            );
-        }
-
-        let position_attribute_location =
-            context.get_attrib_location(program, "position");
-
-        let colour_uniform_location = context
-            .get_uniform_location(&program, "colour")
-            .expect("Missing \"colour\" uniform in program");
-
-        let model_matrix_uniform_location = context
-            .get_uniform_location(&program, "uniform_matrix")
-            .expect("Missing \"uniform_matrix\" uniform in program");
-
-        return Line {
-            position_buffer: buffer,
-            colour,
-            program,
-            position_attribute_location,
-            colour_uniform_location,
-            model_matrix_uniform_location,
+        material: Rc<Material>,
+    ) -> Result<Line, SpatioError> {
+        let mesh = Mesh {
+            positions: vec![a.x, a.y, a.z, b.x, b.y, b.z],
+            ..Mesh::new()
         };
+        let mut gpu_mesh = GpuMesh::new(backend, &mesh)?;
+        gpu_mesh.set_edges(backend, &[[0, 1]])?;
+
+        return Ok(Line {
+            transform: Transform::IDENTITY,
+            gpu_mesh,
+            midpoint: (Vec3::from(a) + Vec3::from(b)) / 2.0,
+            material,
+        });
     }
 }
 
-impl<'a> Draw for Line<'a> {
+impl Draw for Line {
     fn draw(
         &self,
-        context: &web_sys::WebGl2RenderingContext,
-        uniform_matrix: Option<Matrix4F>,
-    ) -> Result<(), String> {
-        context.bind_buffer(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            Some(&self.position_buffer),
-        );
-
-        context.enable_vertex_attrib_array(
-            self.position_attribute_location as u32,
-        );
-
-        context.vertex_attrib_pointer_with_i32(
-            self.position_attribute_location as u32,
-            3,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            0,
-            0,
-        );
+        backend: &mut dyn Backend,
+        camera: &Camera,
+        lights: &[Light],
+        parent_matrix: Option<Matrix4F>,
+    ) -> Result<(), SpatioError> {
+        let program = self.material.program();
+
+        self.material.apply(backend, lights)?;
+        self.gpu_mesh.bind(backend, program)?;
+        program.set_matrices(
+            backend,
+            camera,
+            parent_matrix,
+            &self.transform,
+        )?;
+
+        self.gpu_mesh.draw_edges(backend);
 
-        context.use_program(Some(self.program));
-
-        context.uniform4f(
-            Some(&self.colour_uniform_location),
-            self.colour.r,
-            self.colour.g,
-            self.colour.b,
-            self.colour.a,
-        );
-
-        context.uniform_matrix4fv_with_f32_array(
-            Some(&self.model_matrix_uniform_location),
-            false,
-            &uniform_matrix.unwrap_or(ID_MATRIX),
-        );
+        return Ok(());
+    }
 
-        context.draw_arrays(WebGl2RenderingContext::LINES, 0, 2);
+    fn is_transparent(&self) -> bool {
+        self.material.is_transparent()
+    }
 
-        return Ok(());
+    fn centre(&self) -> Vec3 {
+        Mat4(self.transform.matrix()).transform_point(self.midpoint)
     }
 }
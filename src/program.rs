@@ -0,0 +1,679 @@
+use crate::backend::{
+    Backend,
+    ProgramHandle,
+    ProgramInfo,
+    ProgramKind,
+    Uniform,
+};
+use crate::camera::Camera;
+use crate::error::SpatioError;
+use crate::light::{Light, LightKind};
+use crate::matrix::{mat_mul, normal_matrix, Matrix4F, ID_MATRIX, ID_MATRIX3};
+use crate::transform::Transform;
+use crate::vector::Vec3;
+
+// The shader every primitive can be drawn with: `position` transformed by the
+// model, view and projection matrices, lit by the scene's lights with
+// Lambert diffuse and Phong specular terms. Faces are lit from whichever
+// side is facing the camera. The surface colour is `colour`, multiplied by
+// `vertex_colour` when `u_vertex_colours` is set and by `u_texture` at `uv`
+// when `u_textured` is set.
+pub const STANDARD_VERTEX_SHADER: &str = r##"#version 300 es
+
+in vec4 position;
+in vec3 normal;
+in vec2 uv;
+in vec4 vertex_colour;
+
+uniform mat4 u_model;
+uniform mat4 u_view;
+uniform mat4 u_projection;
+uniform mat3 u_normal_matrix;
+
+out vec3 v_position;
+out vec3 v_normal;
+out vec2 v_uv;
+out vec4 v_colour;
+
+void main() {
+    vec4 world = u_model * position;
+
+    v_position = world.xyz;
+    v_normal = u_normal_matrix * normal;
+    v_uv = uv;
+    v_colour = vertex_colour;
+    gl_Position = u_projection * u_view * world;
+}
+"##;
+
+pub const STANDARD_FRAGMENT_SHADER: &str = r##"#version 300 es
+
+precision highp float;
+
+in vec3 v_position;
+in vec3 v_normal;
+in vec2 v_uv;
+in vec4 v_colour;
+
+uniform vec4 colour;
+uniform bool u_vertex_colours;
+uniform bool u_textured;
+uniform sampler2D u_texture;
+uniform float u_specular;
+uniform float u_shininess;
+
+uniform vec3 u_camera_position;
+uniform vec3 u_ambient;
+uniform int u_light_count;
+// xyz is the direction the light travels in when w is 0, its position when
+// w is 1
+uniform vec4 u_light_vectors[8];
+uniform vec3 u_light_colours[8];
+
+out vec4 outColor;
+
+void main() {
+    vec4 surface = u_vertex_colours ? colour * v_colour : colour;
+    if (u_textured) {
+        surface *= texture(u_texture, v_uv);
+    }
+    vec3 normal = normalize(v_normal);
+    if (!gl_FrontFacing) {
+        normal = -normal;
+    }
+    vec3 to_camera = normalize(u_camera_position - v_position);
+
+    vec3 lit = surface.rgb * u_ambient;
+    for (int i = 0; i < 8; i++) {
+        if (i >= u_light_count) {
+            break;
+        }
+
+        vec4 light = u_light_vectors[i];
+        vec3 to_light = normalize(
+            light.w == 0.0 ? -light.xyz : light.xyz - v_position
+        );
+
+        float diffuse = max(dot(normal, to_light), 0.0);
+        float specular = 0.0;
+        if (diffuse > 0.0) {
+            vec3 reflected = reflect(-to_light, normal);
+            specular = pow(max(dot(reflected, to_camera), 0.0), u_shininess);
+        }
+
+        lit += u_light_colours[i] *
+            (surface.rgb * diffuse + vec3(u_specular * specular));
+    }
+
+    outColor = vec4(lit, surface.a);
+}
+"##;
+
+// Flat colour, for what has no normals or shouldn't be shaded, like lines.
+// Vertex colours and textures work like in the standard shader.
+pub const UNLIT_VERTEX_SHADER: &str = r##"#version 300 es
+
+in vec4 position;
+in vec2 uv;
+in vec4 vertex_colour;
+
+uniform mat4 u_model;
+uniform mat4 u_view;
+uniform mat4 u_projection;
+
+out vec2 v_uv;
+out vec4 v_colour;
+
+void main() {
+    v_uv = uv;
+    v_colour = vertex_colour;
+    gl_Position = u_projection * u_view * u_model * position;
+}
+"##;
+
+pub const UNLIT_FRAGMENT_SHADER: &str = r##"#version 300 es
+
+precision highp float;
+
+in vec2 v_uv;
+in vec4 v_colour;
+
+uniform vec4 colour;
+uniform bool u_vertex_colours;
+uniform bool u_textured;
+uniform sampler2D u_texture;
+
+out vec4 outColor;
+
+void main() {
+    outColor = u_vertex_colours ? colour * v_colour : colour;
+    if (u_textured) {
+        outColor *= texture(u_texture, v_uv);
+    }
+}
+"##;
+
+// Screen aligned quads of a constant size in pixels, e.g. for labels. The
+// model matrix only places the quad's anchor: `position` is the corner's
+// offset from it in pixels, x right and y up, so it faces the camera and
+// keeps its size whatever the distance. Textured like the other shaders.
+pub const BILLBOARD_VERTEX_SHADER: &str = r##"#version 300 es
+
+in vec4 position;
+in vec2 uv;
+
+uniform mat4 u_model;
+uniform mat4 u_view;
+uniform mat4 u_projection;
+uniform vec2 u_viewport;
+
+out vec2 v_uv;
+
+void main() {
+    vec4 anchor = u_projection * u_view * u_model * vec4(0.0, 0.0, 0.0, 1.0);
+    vec2 offset = position.xy * 2.0 / u_viewport * anchor.w;
+
+    v_uv = uv;
+    gl_Position = anchor + vec4(offset, 0.0, 0.0);
+}
+"##;
+
+pub const BILLBOARD_FRAGMENT_SHADER: &str = r##"#version 300 es
+
+precision highp float;
+
+in vec2 v_uv;
+
+uniform vec4 colour;
+uniform bool u_textured;
+uniform sampler2D u_texture;
+
+out vec4 outColor;
+
+void main() {
+    outColor = colour;
+    if (u_textured) {
+        outColor *= texture(u_texture, v_uv);
+    }
+    // Keeps the empty parts of the quad from hiding what's behind
+    if (outColor.a == 0.0) {
+        discard;
+    }
+}
+"##;
+
+// Thick, anti-aliased lines, drawn by `Polyline`. Every segment is a quad
+// spread around it on screen. Each corner knows its segment's other end and
+// the points past both ends, so the ends can be joined to the neighbouring
+// segments or capped. The width is in pixels, or in the polyline's units
+// when `u_world_width` is set. Ends are finished with a mode: one of the
+// caps, or OPEN when the miter of a join carries on into the next segment.
+// Joins sharper than `u_miter_limit` are rounded.
+pub const POLYLINE_VERTEX_SHADER: &str = r##"#version 300 es
+
+in vec3 position;
+in vec3 other;
+in vec3 beyond;
+in vec3 beyond_other;
+// x is the side of the line, -1 or 1, y is 1 at the end of the segment and
+// 0 at its start
+in vec2 corner;
+in float line_distance;
+
+uniform mat4 u_model;
+uniform mat4 u_view;
+uniform mat4 u_projection;
+uniform vec2 u_viewport;
+uniform float u_line_width;
+uniform bool u_world_width;
+// 0 butt, 1 square, 2 round
+uniform int u_cap;
+// 0 miter, 1 round
+uniform int u_join;
+uniform float u_miter_limit;
+
+// Along the segment from its start and across it, in pixels, times w
+out vec3 v_local;
+out float v_length;
+out vec2 v_half_widths;
+out vec2 v_modes;
+out float v_distance;
+
+const float BUTT = 0.0;
+const float ROUND = 2.0;
+const float OPEN = 3.0;
+
+// Pixels from the centre of the viewport
+vec2 screen(vec4 clip) {
+    return clip.xy / clip.w * u_viewport * 0.5;
+}
+
+float half_width(vec4 clip) {
+    if (!u_world_width) {
+        return u_line_width * 0.5;
+    }
+    // Pixels per unit at that depth, for both projections
+    return u_line_width * 0.5 * u_projection[1][1] * u_viewport.y * 0.5 /
+        clip.w;
+}
+
+// How the end at `point` is finished, with `beyond` the point past it.
+// `offset` is where the corner on `side` goes from the end, one pixel wider
+// than the line for the anti-aliased edge.
+float finish(
+    vec2 point,
+    vec2 beyond,
+    bool at_end,
+    vec2 direction,
+    float half_width,
+    float side, out vec2 offset
+) {
+    vec2 normal = vec2(-direction.y, direction.x);
+    vec2 outward = at_end ? direction : -direction;
+    float spread = half_width + 1.0;
+    vec2 turn = beyond - point;
+
+    if (length(turn) > 0.001) {
+        vec2 incoming = at_end ? direction : normalize(-turn);
+        vec2 outgoing = at_end ? normalize(turn) : direction;
+        vec2 tangent = incoming + outgoing;
+
+        if (length(tangent) > 0.001) {
+            tangent = normalize(tangent);
+            float ratio = 1.0 / dot(tangent, direction);
+            if (ratio <= u_miter_limit) {
+                offset = vec2(-tangent.y, tangent.x) * ratio * spread * side;
+                return u_join == 0 ? OPEN : ROUND;
+            }
+        }
+
+        offset = normal * spread * side + outward * spread;
+        return ROUND;
+    }
+
+    float mode = float(u_cap);
+    offset = normal * spread * side +
+        outward * (mode == BUTT ? 1.0 : spread);
+    return mode;
+}
+
+void main() {
+    mat4 matrix = u_projection * u_view * u_model;
+    bool at_end = corner.y > 0.5;
+
+    vec4 clip = matrix * vec4(position, 1.0);
+    vec4 other_clip = matrix * vec4(other, 1.0);
+    vec4 start_clip = at_end ? other_clip : clip;
+    vec4 end_clip = at_end ? clip : other_clip;
+    vec2 start = screen(start_clip);
+    vec2 end = screen(end_clip);
+    vec2 before = screen(matrix * vec4(at_end ? beyond_other : beyond, 1.0));
+    vec2 after = screen(matrix * vec4(at_end ? beyond : beyond_other, 1.0));
+
+    float len = length(end - start);
+    vec2 direction = len > 0.0 ? (end - start) / len : vec2(1.0, 0.0);
+    float start_width = half_width(start_clip);
+    float end_width = half_width(end_clip);
+
+    vec2 start_offset;
+    vec2 end_offset;
+    float start_mode = finish(
+        start, before, false, direction, start_width, corner.x, start_offset
+    );
+    float end_mode = finish(
+        end, after, true, direction, end_width, corner.x, end_offset
+    );
+    vec2 offset = at_end ? end_offset : start_offset;
+
+    float along = dot(offset, direction) + (at_end ? len : 0.0);
+    float across = dot(offset, vec2(-direction.y, direction.x));
+    // Scaled by w so dividing by the interpolated w undoes the perspective
+    // correction, pixels are linear on screen
+    v_local = vec3(along, across, 1.0) * clip.w;
+    v_length = len;
+    v_half_widths = vec2(start_width, end_width);
+    v_modes = vec2(start_mode, end_mode);
+    v_distance = line_distance;
+
+    gl_Position = clip + vec4(offset * 2.0 / u_viewport * clip.w, 0.0, 0.0);
+}
+"##;
+
+// The fragment's distance outside the line, in pixels, sets its coverage.
+// Dashes are `u_dash.x` long with `u_dash.y` gaps, in the polyline's units.
+pub const POLYLINE_FRAGMENT_SHADER: &str = r##"#version 300 es
+
+precision highp float;
+
+in vec3 v_local;
+in float v_length;
+in vec2 v_half_widths;
+in vec2 v_modes;
+in float v_distance;
+
+uniform vec4 colour;
+uniform vec2 u_dash;
+
+out vec4 outColor;
+
+void main() {
+    float along = v_local.x / v_local.z;
+    float across = v_local.y / v_local.z;
+    float half_width = mix(
+        v_half_widths.x,
+        v_half_widths.y,
+        clamp(along / max(v_length, 0.001), 0.0, 1.0)
+    );
+
+    float past = 0.0;
+    float mode = 3.0;
+    if (along < 0.0) {
+        past = -along;
+        mode = v_modes.x;
+    } else if (along > v_length) {
+        past = along - v_length;
+        mode = v_modes.y;
+    }
+
+    float outside = abs(across) - half_width;
+    if (mode < 0.5) {
+        outside = max(outside, past);
+    } else if (mode < 1.5) {
+        outside = max(outside, past - half_width);
+    } else if (mode < 2.5) {
+        outside = length(vec2(past, across)) - half_width;
+    }
+    float coverage = clamp(0.5 - outside, 0.0, 1.0);
+
+    if (u_dash.y > 0.0 && mod(v_distance, u_dash.x + u_dash.y) > u_dash.x) {
+        coverage = 0.0;
+    }
+    if (coverage == 0.0) {
+        discard;
+    }
+
+    outColor = vec4(colour.rgb, colour.a * coverage);
+}
+"##;
+
+// A linked program along with what it declares, so uniforms are checked by
+// name and type instead of being silently dropped like GL does
+pub struct Program {
+    handle:     ProgramHandle,
+    info:       ProgramInfo,
+    // Counted once, lights are set on every draw
+    max_lights: usize,
+}
+
+#[allow(dead_code)]
+impl Program {
+    // A program running custom shaders
+    pub fn new(
+        backend: &mut dyn Backend,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<Program, SpatioError> {
+        return Program::with_kind(
+            backend,
+            ProgramKind::Custom,
+            vertex_source,
+            fragment_source,
+        );
+    }
+
+    fn with_kind(
+        backend: &mut dyn Backend,
+        kind: ProgramKind,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<Program, SpatioError> {
+        let handle =
+            backend.create_program(kind, vertex_source, fragment_source)?;
+        let info = backend.program_info(handle).ok_or_else(|| {
+            SpatioError::ProgramLink(String::from("Program lost after linking"))
+        })?;
+        let max_lights = (0..)
+            .take_while(|i| {
+                info.uniforms
+                    .contains_key(&format!("u_light_vectors[{}]", i))
+            })
+            .count();
+
+        return Ok(Program {
+            handle,
+            info,
+            max_lights,
+        });
+    }
+
+    pub fn standard(backend: &mut dyn Backend) -> Result<Program, SpatioError> {
+        return Program::with_kind(
+            backend,
+            ProgramKind::Standard,
+            STANDARD_VERTEX_SHADER,
+            STANDARD_FRAGMENT_SHADER,
+        );
+    }
+
+    pub fn unlit(backend: &mut dyn Backend) -> Result<Program, SpatioError> {
+        return Program::with_kind(
+            backend,
+            ProgramKind::Unlit,
+            UNLIT_VERTEX_SHADER,
+            UNLIT_FRAGMENT_SHADER,
+        );
+    }
+
+    pub fn billboard(
+        backend: &mut dyn Backend,
+    ) -> Result<Program, SpatioError> {
+        return Program::with_kind(
+            backend,
+            ProgramKind::Billboard,
+            BILLBOARD_VERTEX_SHADER,
+            BILLBOARD_FRAGMENT_SHADER,
+        );
+    }
+
+    pub fn polyline(backend: &mut dyn Backend) -> Result<Program, SpatioError> {
+        return Program::with_kind(
+            backend,
+            ProgramKind::Polyline,
+            POLYLINE_VERTEX_SHADER,
+            POLYLINE_FRAGMENT_SHADER,
+        );
+    }
+
+    pub fn handle(&self) -> ProgramHandle {
+        self.handle
+    }
+
+    pub fn info(&self) -> &ProgramInfo {
+        &self.info
+    }
+
+    pub fn kind(&self) -> ProgramKind {
+        self.info.kind
+    }
+
+    pub fn has_uniform(&self, name: &str) -> bool {
+        self.info.uniforms.contains_key(name)
+    }
+
+    pub fn has_attribute(&self, name: &str) -> bool {
+        self.info.attributes.contains(name)
+    }
+
+    pub fn require_uniforms(&self, names: &[&str]) -> Result<(), SpatioError> {
+        for name in names {
+            if !self.has_uniform(name) {
+                return Err(SpatioError::MissingUniform(name.to_string()));
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn use_program(&self, backend: &mut dyn Backend) {
+        backend.use_program(self.handle);
+    }
+
+    // Expects the program to be the current one
+    pub fn set_uniform(
+        &self,
+        backend: &mut dyn Backend,
+        name: &str,
+        value: Uniform,
+    ) -> Result<(), SpatioError> {
+        let Some(uniform_type) = self.info.uniforms.get(name) else {
+            return Err(SpatioError::MissingUniform(name.to_string()));
+        };
+        if !value.fits(*uniform_type) {
+            return Err(SpatioError::WrongUniformType(name.to_string()));
+        }
+
+        backend.set_uniform(name, value);
+        return Ok(());
+    }
+
+    // Sets the model, view and projection matrices, the normal matrix and
+    // camera position when the program is lit, and the viewport size when
+    // the program works in pixels. `parent_matrix` is the world
+    // matrix of whatever the object is attached to, e.g. its scene node, and
+    // the object's own transform is applied on top of it.
+    pub fn set_matrices(
+        &self,
+        backend: &mut dyn Backend,
+        camera: &Camera,
+        parent_matrix: Option<Matrix4F>,
+        transform: &Transform,
+    ) -> Result<(), SpatioError> {
+        let model =
+            mat_mul(&parent_matrix.unwrap_or(ID_MATRIX), &transform.matrix());
+
+        self.set_uniform(backend, "u_model", Uniform::Mat4(model))?;
+        self.set_uniform(
+            backend,
+            "u_view",
+            Uniform::Mat4(camera.view_matrix()),
+        )?;
+        self.set_uniform(
+            backend,
+            "u_projection",
+            Uniform::Mat4(camera.projection_matrix()),
+        )?;
+
+        if self.has_uniform("u_normal_matrix") {
+            // A degenerate model matrix flattens the object, any normal will do
+            let normal_matrix = normal_matrix(&model).unwrap_or(ID_MATRIX3);
+            self.set_uniform(
+                backend,
+                "u_normal_matrix",
+                Uniform::Mat3(normal_matrix),
+            )?;
+        }
+        if self.has_uniform("u_camera_position") {
+            self.set_uniform(
+                backend,
+                "u_camera_position",
+                Uniform::Vec3(camera.eye),
+            )?;
+        }
+        if self.has_uniform("u_viewport") {
+            let (width, height) = camera.viewport();
+            self.set_uniform(
+                backend,
+                "u_viewport",
+                Uniform::Vec2([width, height]),
+            )?;
+        }
+
+        return Ok(());
+    }
+
+    // The number of `u_light_*` slots the program has, 0 when it isn't lit
+    pub fn max_lights(&self) -> usize {
+        self.max_lights
+    }
+
+    // Ambient lights add up into a single term. Lights past `max_lights` are
+    // dropped. Does nothing for programs that aren't lit.
+    pub fn set_lights(
+        &self,
+        backend: &mut dyn Backend,
+        lights: &[Light],
+    ) -> Result<(), SpatioError> {
+        if !self.has_uniform("u_light_count") {
+            return Ok(());
+        }
+
+        let mut ambient = Vec3::ZERO;
+        let mut count = 0;
+
+        for light in lights {
+            let vector = match light.kind {
+                LightKind::Ambient => {
+                    ambient += light.radiance();
+                    continue;
+                }
+                LightKind::Directional(direction) => direction.extend(0.0),
+                LightKind::Point(position) => position.extend(1.0),
+            };
+            if count == self.max_lights {
+                continue;
+            }
+
+            self.set_uniform(
+                backend,
+                &format!("u_light_vectors[{}]", count),
+                Uniform::Vec4(vector.to_array()),
+            )?;
+            self.set_uniform(
+                backend,
+                &format!("u_light_colours[{}]", count),
+                Uniform::Vec3(light.radiance()),
+            )?;
+            count += 1;
+        }
+
+        self.set_uniform(backend, "u_ambient", Uniform::Vec3(ambient))?;
+        self.set_uniform(backend, "u_light_count", Uniform::Int(count as i32))?;
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::{Command, RecordingBackend};
+
+    #[test]
+    fn light_slots() {
+        let mut backend = RecordingBackend::new();
+        assert_eq!(Program::standard(&mut backend).unwrap().max_lights(), 8);
+        assert_eq!(Program::unlit(&mut backend).unwrap().max_lights(), 0);
+
+        // Lights past the slots are dropped, ambient ones add up
+        let program = Program::standard(&mut backend).unwrap();
+        let mut lights = vec![Light::ambient(0.25); 2];
+        lights.extend(vec![Light::point(Vec3::ZERO, 1.0); 10]);
+        backend.commands.clear();
+        program.set_lights(&mut backend, &lights).unwrap();
+
+        let set =
+            |name: &str| {
+                backend.commands.iter().rev().find_map(
+                    |command| match command {
+                        Command::SetUniform {
+                            name: set,
+                            value,
+                        } if set == name => Some(*value),
+                        _ => None,
+                    },
+                )
+            };
+        assert_eq!(set("u_light_count"), Some(Uniform::Int(8)));
+        assert_eq!(set("u_ambient"), Some(Uniform::Vec3(Vec3::ONE * 0.5)));
+        assert_eq!(set("u_light_vectors[8]"), None);
+    }
+}
@@ -0,0 +1,391 @@
+use crate::matrix::{mat3_vec_mul, Mat4, Matrix4F};
+use crate::vector::Vec3;
+
+// Sine of the angle below which a ray counts as parallel to a surface.
+// Relative, since transformed rays and picked meshes come in any scale.
+const PARALLEL_EPSILON: f32 = 1e-6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin:    Vec3,
+    pub direction: Vec3,
+}
+
+// `distance` is the ray parameter of the hit, which is a true distance as long
+// as the ray direction is unit length (see `Ray::transform`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub distance: f32,
+    pub point:    Vec3,
+    pub normal:   Vec3,
+}
+
+#[allow(dead_code)]
+pub trait Intersect {
+    fn intersect(&self, ray: &Ray) -> Option<RayHit>;
+}
+
+#[allow(dead_code)]
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+        Ray {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    // The direction is deliberately left unnormalised so that hit distances
+    // found with the transformed ray can be used as is with the original one.
+    // Typically used with an inverse model matrix to test in object space.
+    pub fn transform(&self, matrix: &Mat4) -> Ray {
+        Ray {
+            origin:    matrix.transform_point(self.origin),
+            direction: matrix.transform_vector(self.direction),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl RayHit {
+    fn new(ray: &Ray, distance: f32, normal: Vec3) -> RayHit {
+        RayHit {
+            distance,
+            point: ray.at(distance),
+            normal,
+        }
+    }
+
+    // Brings a hit computed with a transformed ray back to world space
+    pub fn transform(&self, matrix: &Mat4) -> RayHit {
+        let normal = match matrix.normal_matrix() {
+            Some(n) => mat3_vec_mul(&n, self.normal),
+            None => self.normal,
+        };
+
+        return RayHit {
+            distance: self.distance,
+            point:    matrix.transform_point(self.point),
+            normal:   normal.normalize(),
+        };
+    }
+}
+
+// Runs an object space intersection test against an object placed in the
+// world with `model`, returning the hit in world space
+pub fn intersect_transformed(
+    ray: &Ray,
+    model: &Matrix4F,
+    test: impl FnOnce(&Ray) -> Option<RayHit>,
+) -> Option<RayHit> {
+    let model = Mat4(*model);
+    let hit = test(&ray.transform(&model.inverse()?))?;
+    return Some(hit.transform(&model));
+}
+
+// Closest of two optional hits, used to combine several intersection tests
+#[allow(dead_code)]
+pub fn closest_hit(a: Option<RayHit>, b: Option<RayHit>) -> Option<RayHit> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.distance < a.distance { b } else { a }),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+#[allow(dead_code)]
+pub fn intersect_sphere(
+    ray: &Ray,
+    center: Vec3,
+    radius: f32,
+) -> Option<RayHit> {
+    let offset = ray.origin - center;
+
+    let a = ray.direction.length_squared();
+    let half_b = offset.dot(ray.direction);
+    let c = offset.length_squared() - radius * radius;
+
+    let discriminant = half_b * half_b - a * c;
+    if a == 0.0 || discriminant < 0.0 {
+        return None;
+    }
+
+    let root = discriminant.sqrt();
+
+    // Near root first, far root if the origin is inside the sphere
+    let mut distance = (-half_b - root) / a;
+    if distance < 0.0 {
+        distance = (-half_b + root) / a;
+    }
+    if distance < 0.0 {
+        return None;
+    }
+
+    let point = ray.at(distance);
+    return Some(RayHit::new(ray, distance, (point - center) / radius));
+}
+
+// Axis aligned box, slab method
+#[allow(dead_code)]
+pub fn intersect_box(ray: &Ray, min: Vec3, max: Vec3) -> Option<RayHit> {
+    let origin = ray.origin.to_array();
+    let direction = ray.direction.to_array();
+    let min = min.to_array();
+    let max = max.to_array();
+
+    let mut near = f32::NEG_INFINITY;
+    let mut far = f32::INFINITY;
+    let mut near_axis = 0;
+    let mut far_axis = 0;
+
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let inverse = direction[axis].recip();
+        let mut t0 = (min[axis] - origin[axis]) * inverse;
+        let mut t1 = (max[axis] - origin[axis]) * inverse;
+        if t0 > t1 {
+            std::mem::swap(&mut t0, &mut t1);
+        }
+
+        if t0 > near {
+            near = t0;
+            near_axis = axis;
+        }
+        if t1 < far {
+            far = t1;
+            far_axis = axis;
+        }
+
+        if near > far {
+            return None;
+        }
+    }
+
+    if far < 0.0 {
+        return None;
+    }
+
+    let (distance, axis) = if near >= 0.0 {
+        (near, near_axis)
+    } else {
+        (far, far_axis)
+    };
+
+    // The normal faces against the ray on entry and along it on exit
+    let mut normal = [0.0; 3];
+    normal[axis] = if near >= 0.0 {
+        -direction[axis].signum()
+    } else {
+        direction[axis].signum()
+    };
+
+    return Some(RayHit::new(ray, distance, Vec3::from(normal)));
+}
+
+// Möller–Trumbore. Both faces are hit, the normal follows the a, b, c winding.
+#[allow(dead_code)]
+pub fn intersect_triangle(
+    ray: &Ray,
+    a: Vec3,
+    b: Vec3,
+    c: Vec3,
+) -> Option<RayHit> {
+    let edge_1 = b - a;
+    let edge_2 = c - a;
+
+    let p = ray.direction.cross(edge_2);
+    let determinant = edge_1.dot(p);
+    let scale = ray.direction.length() * edge_1.length() * edge_2.length();
+    if determinant.abs() <= PARALLEL_EPSILON * scale {
+        return None;
+    }
+
+    let inverse_determinant = determinant.recip();
+
+    let offset = ray.origin - a;
+    let u = offset.dot(p) * inverse_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = offset.cross(edge_1);
+    let v = ray.direction.dot(q) * inverse_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let distance = edge_2.dot(q) * inverse_determinant;
+    if distance < 0.0 {
+        return None;
+    }
+
+    let normal = edge_1.cross(edge_2).normalize();
+    return Some(RayHit::new(ray, distance, normal));
+}
+
+#[allow(dead_code)]
+pub fn intersect_plane(ray: &Ray, point: Vec3, normal: Vec3) -> Option<RayHit> {
+    let denominator = normal.dot(ray.direction);
+    let scale = normal.length() * ray.direction.length();
+    if denominator.abs() <= PARALLEL_EPSILON * scale {
+        return None;
+    }
+
+    let distance = (point - ray.origin).dot(normal) / denominator;
+    if distance < 0.0 {
+        return None;
+    }
+
+    return Some(RayHit::new(ray, distance, normal.normalize()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_vec_eq(a: Vec3, b: Vec3) {
+        assert!(a.distance(b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn sphere() {
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), -Vec3::Z);
+
+        let hit = intersect_sphere(&ray, Vec3::ZERO, 1.0).unwrap();
+        assert!((hit.distance - 4.0).abs() < EPSILON);
+        assert_vec_eq(hit.point, Vec3::Z);
+        assert_vec_eq(hit.normal, Vec3::Z);
+
+        // Missing to the side, and behind the origin
+        assert_eq!(intersect_sphere(&ray, Vec3::new(2.0, 0.0, 0.0), 1.0), None);
+        assert_eq!(intersect_sphere(&ray, Vec3::new(0.0, 0.0, 7.0), 1.0), None);
+    }
+
+    #[test]
+    fn sphere_from_inside() {
+        let ray = Ray::new(Vec3::ZERO, Vec3::X);
+        let hit = intersect_sphere(&ray, Vec3::ZERO, 2.0).unwrap();
+
+        // The far side, with the normal still pointing out
+        assert!((hit.distance - 2.0).abs() < EPSILON);
+        assert_vec_eq(hit.normal, Vec3::X);
+    }
+
+    #[test]
+    fn box_entry_and_exit() {
+        let (min, max) = (-Vec3::ONE, Vec3::ONE);
+
+        let ray = Ray::new(Vec3::new(-5.0, 0.5, 0.0), Vec3::X);
+        let hit = intersect_box(&ray, min, max).unwrap();
+        assert!((hit.distance - 4.0).abs() < EPSILON);
+        assert_vec_eq(hit.normal, -Vec3::X);
+
+        let inside = Ray::new(Vec3::new(0.5, 0.0, 0.0), Vec3::X);
+        let hit = intersect_box(&inside, min, max).unwrap();
+        assert!((hit.distance - 0.5).abs() < EPSILON);
+        assert_vec_eq(hit.normal, Vec3::X);
+
+        let miss = Ray::new(Vec3::new(-5.0, 2.0, 0.0), Vec3::X);
+        assert_eq!(intersect_box(&miss, min, max), None);
+        let behind = Ray::new(Vec3::new(5.0, 0.0, 0.0), Vec3::X);
+        assert_eq!(intersect_box(&behind, min, max), None);
+    }
+
+    #[test]
+    fn box_slab_boundary() {
+        let (min, max) = (-Vec3::ONE, Vec3::ONE);
+
+        // Grazing the top face, parallel to it
+        let ray = Ray::new(Vec3::new(-5.0, 1.0, 0.0), Vec3::X);
+        let hit = intersect_box(&ray, min, max).unwrap();
+        assert!((hit.distance - 4.0).abs() < EPSILON);
+        assert_vec_eq(hit.point, Vec3::new(-1.0, 1.0, 0.0));
+
+        // Through an edge, where two slabs start together
+        let diagonal =
+            Ray::new(Vec3::new(-3.0, -3.0, 0.0), Vec3::new(1.0, 1.0, 0.0));
+        let hit = intersect_box(&diagonal, min, max).unwrap();
+        assert_vec_eq(hit.point, Vec3::new(-1.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn triangle() {
+        let (a, b, c) = (Vec3::ZERO, Vec3::X, Vec3::Y);
+        let ray = Ray::new(Vec3::new(0.25, 0.25, 2.0), -Vec3::Z);
+
+        let hit = intersect_triangle(&ray, a, b, c).unwrap();
+        assert!((hit.distance - 2.0).abs() < EPSILON);
+        assert_vec_eq(hit.normal, Vec3::Z);
+        // Both faces, the normal following the winding
+        let hit = intersect_triangle(&ray, a, c, b).unwrap();
+        assert_vec_eq(hit.normal, -Vec3::Z);
+
+        let outside = Ray::new(Vec3::new(0.75, 0.75, 2.0), -Vec3::Z);
+        assert_eq!(intersect_triangle(&outside, a, b, c), None);
+        let parallel = Ray::new(Vec3::new(-1.0, 0.25, 0.0), Vec3::X);
+        assert_eq!(intersect_triangle(&parallel, a, b, c), None);
+    }
+
+    #[test]
+    fn triangle_at_any_scale() {
+        let scale = 1e-4;
+        let (a, b, c) = (Vec3::ZERO, Vec3::X * scale, Vec3::Y * scale);
+        let ray = Ray::new(Vec3::new(0.25, 0.25, 2.0) * scale, -Vec3::Z);
+        assert!(intersect_triangle(&ray, a, b, c).is_some());
+
+        // A long unnormalised direction, like a transformed ray's
+        let ray = Ray {
+            origin:    Vec3::new(0.25, 0.25, 2.0),
+            direction: -Vec3::Z * 1e4,
+        };
+        let hit = intersect_triangle(&ray, Vec3::ZERO, Vec3::X, Vec3::Y);
+        assert!((hit.unwrap().distance - 2e-4).abs() < EPSILON);
+    }
+
+    #[test]
+    fn plane() {
+        let ray = Ray::new(Vec3::new(0.0, 3.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        let hit = intersect_plane(&ray, Vec3::ZERO, Vec3::Y * 2.0).unwrap();
+        assert_vec_eq(hit.point, Vec3::new(3.0, 0.0, 0.0));
+        assert_vec_eq(hit.normal, Vec3::Y);
+
+        let parallel = Ray::new(Vec3::new(0.0, 3.0, 0.0), Vec3::X);
+        assert_eq!(intersect_plane(&parallel, Vec3::ZERO, Vec3::Y), None);
+        let away = Ray::new(Vec3::new(0.0, 3.0, 0.0), Vec3::Y);
+        assert_eq!(intersect_plane(&away, Vec3::ZERO, Vec3::Y), None);
+    }
+
+    #[test]
+    fn transformed_distances_are_world_distances() {
+        // A unit sphere scaled up to radius 2 and moved along x
+        let model = Mat4::translation(Vec3::new(3.0, 0.0, 0.0)) *
+            Mat4::scale(Vec3::ONE * 2.0);
+        let ray = Ray::new(Vec3::new(3.0, 0.0, 10.0), -Vec3::Z);
+
+        let hit = intersect_transformed(&ray, &model.0, |ray| {
+            intersect_sphere(ray, Vec3::ZERO, 1.0)
+        })
+        .unwrap();
+        assert!((hit.distance - 8.0).abs() < EPSILON);
+        assert_vec_eq(hit.point, Vec3::new(3.0, 0.0, 2.0));
+        assert_vec_eq(hit.point, ray.at(hit.distance));
+        assert_vec_eq(hit.normal, Vec3::Z);
+
+        let miss = Ray::new(Vec3::new(0.0, 0.0, 10.0), -Vec3::Z);
+        let hit = intersect_transformed(&miss, &model.0, |ray| {
+            intersect_sphere(ray, Vec3::ZERO, 1.0)
+        });
+        assert_eq!(hit, None);
+    }
+}
@@ -0,0 +1,336 @@
+use crate::backend::{
+    Backend,
+    BufferData,
+    BufferHandle,
+    DrawMode,
+    Face,
+    ProgramHandle,
+    ProgramInfo,
+    ProgramKind,
+    TextureData,
+    TextureHandle,
+    Uniform,
+};
+use crate::colour::Colour;
+use crate::error::SpatioError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    CreateBuffer {
+        buffer: BufferHandle,
+        data:   RecordedData,
+    },
+    DeleteBuffer(BufferHandle),
+    CreateTexture {
+        texture: TextureHandle,
+        width:   u32,
+        height:  u32,
+    },
+    DeleteTexture(TextureHandle),
+    BindTexture {
+        unit:    u32,
+        texture: TextureHandle,
+    },
+    CreateProgram {
+        program:         ProgramHandle,
+        kind:            ProgramKind,
+        vertex_source:   String,
+        fragment_source: String,
+    },
+    UseProgram(ProgramHandle),
+    BindAttribute {
+        name:   String,
+        buffer: BufferHandle,
+        size:   u32,
+    },
+    DisableAttribute(String),
+    SetUniform {
+        name:  String,
+        value: Uniform,
+    },
+    Draw {
+        mode:    DrawMode,
+        indices: Option<BufferHandle>,
+        count:   usize,
+    },
+    SetViewport(u32, u32),
+    SetBlending(bool),
+    SetDepthWrite(bool),
+    SetDepthOffset(bool),
+    SetDepthTest(bool),
+    SetFaceCulling(Option<Face>),
+    Clear(Colour),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordedData {
+    Vertices(Vec<f32>),
+    Indices(Vec<u16>),
+}
+
+// Draws nothing, only keeps a log of every call made to it so the calls
+// primitives make can be checked without a GPU
+#[derive(Default)]
+pub struct RecordingBackend {
+    pub commands:     Vec<Command>,
+    // When set, creating buffers fails once this many have been created, to
+    // check that primitives clean up after a failed upload
+    pub buffer_limit: Option<usize>,
+    next_handle:      u32,
+}
+
+#[allow(dead_code)]
+impl RecordingBackend {
+    pub fn new() -> RecordingBackend {
+        RecordingBackend::default()
+    }
+
+    fn handle(&mut self) -> u32 {
+        self.next_handle += 1;
+        return self.next_handle - 1;
+    }
+
+    // Buffers created and not deleted since
+    pub fn live_buffers(&self) -> Vec<BufferHandle> {
+        let mut buffers = vec![];
+        for command in &self.commands {
+            match command {
+                Command::CreateBuffer {
+                    buffer, ..
+                } => buffers.push(*buffer),
+                Command::DeleteBuffer(deleted) => {
+                    buffers.retain(|buffer| buffer != deleted)
+                }
+                _ => {}
+            }
+        }
+        return buffers;
+    }
+
+    // Only the draw calls, with the uniforms in effect for each
+    pub fn draws(&self) -> Vec<(&Command, Vec<(&str, Uniform)>)> {
+        let mut uniforms: Vec<(&str, Uniform)> = vec![];
+        let mut draws = vec![];
+
+        for command in &self.commands {
+            match command {
+                Command::SetUniform {
+                    name,
+                    value,
+                } => {
+                    uniforms.retain(|(n, _)| n != name);
+                    uniforms.push((name, *value));
+                }
+                Command::Draw {
+                    ..
+                } => draws.push((command, uniforms.clone())),
+                _ => {}
+            }
+        }
+
+        return draws;
+    }
+}
+
+impl Backend for RecordingBackend {
+    fn create_buffer(
+        &mut self,
+        data: BufferData,
+    ) -> Result<BufferHandle, SpatioError> {
+        let created = self
+            .commands
+            .iter()
+            .filter(|command| matches!(command, Command::CreateBuffer { .. }))
+            .count();
+        if self.buffer_limit.is_some_and(|limit| created >= limit) {
+            return Err(SpatioError::BufferCreation);
+        }
+
+        let buffer = BufferHandle(self.handle());
+        let data = match data {
+            BufferData::Vertices(data) => RecordedData::Vertices(data.to_vec()),
+            BufferData::Indices(data) => RecordedData::Indices(data.to_vec()),
+        };
+
+        self.commands.push(Command::CreateBuffer {
+            buffer,
+            data,
+        });
+        return Ok(buffer);
+    }
+
+    fn delete_buffer(&mut self, buffer: BufferHandle) {
+        self.commands.push(Command::DeleteBuffer(buffer));
+    }
+
+    fn create_texture(
+        &mut self,
+        data: TextureData,
+    ) -> Result<TextureHandle, SpatioError> {
+        data.check()?;
+
+        let texture = TextureHandle(self.handle());
+        let (width, height) = data.size();
+
+        self.commands.push(Command::CreateTexture {
+            texture,
+            width,
+            height,
+        });
+        return Ok(texture);
+    }
+
+    fn delete_texture(&mut self, texture: TextureHandle) {
+        self.commands.push(Command::DeleteTexture(texture));
+    }
+
+    fn bind_texture(&mut self, unit: u32, texture: TextureHandle) {
+        self.commands.push(Command::BindTexture {
+            unit,
+            texture,
+        });
+    }
+
+    fn create_program(
+        &mut self,
+        kind: ProgramKind,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<ProgramHandle, SpatioError> {
+        let program = ProgramHandle(self.handle());
+
+        self.commands.push(Command::CreateProgram {
+            program,
+            kind,
+            vertex_source: vertex_source.to_string(),
+            fragment_source: fragment_source.to_string(),
+        });
+        return Ok(program);
+    }
+
+    fn use_program(&mut self, program: ProgramHandle) {
+        self.commands.push(Command::UseProgram(program));
+    }
+
+    // Read back from the recorded sources
+    fn program_info(&self, program: ProgramHandle) -> Option<ProgramInfo> {
+        self.commands.iter().find_map(|command| match command {
+            Command::CreateProgram {
+                program: created,
+                kind,
+                vertex_source,
+                fragment_source,
+            } if *created == program => Some(ProgramInfo::from_source(
+                *kind,
+                vertex_source,
+                fragment_source,
+            )),
+            _ => None,
+        })
+    }
+
+    fn bind_attribute(&mut self, name: &str, buffer: BufferHandle, size: u32) {
+        self.commands.push(Command::BindAttribute {
+            name: name.to_string(),
+            buffer,
+            size,
+        });
+    }
+
+    fn disable_attribute(&mut self, name: &str) {
+        self.commands
+            .push(Command::DisableAttribute(name.to_string()));
+    }
+
+    fn set_uniform(&mut self, name: &str, value: Uniform) {
+        self.commands.push(Command::SetUniform {
+            name: name.to_string(),
+            value,
+        });
+    }
+
+    fn draw(
+        &mut self,
+        mode: DrawMode,
+        indices: Option<BufferHandle>,
+        count: usize,
+    ) {
+        self.commands.push(Command::Draw {
+            mode,
+            indices,
+            count,
+        });
+    }
+
+    fn set_viewport(&mut self, width: u32, height: u32) {
+        self.commands.push(Command::SetViewport(width, height));
+    }
+
+    fn set_blending(&mut self, enabled: bool) {
+        self.commands.push(Command::SetBlending(enabled));
+    }
+
+    fn set_depth_write(&mut self, enabled: bool) {
+        self.commands.push(Command::SetDepthWrite(enabled));
+    }
+
+    fn set_depth_offset(&mut self, enabled: bool) {
+        self.commands.push(Command::SetDepthOffset(enabled));
+    }
+
+    fn set_depth_test(&mut self, enabled: bool) {
+        self.commands.push(Command::SetDepthTest(enabled));
+    }
+
+    fn set_face_culling(&mut self, face: Option<Face>) {
+        self.commands.push(Command::SetFaceCulling(face));
+    }
+
+    fn clear(&mut self, colour: Colour) {
+        self.commands.push(Command::Clear(colour));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_carry_the_uniforms_in_effect() {
+        let mut backend = RecordingBackend::new();
+        backend.set_uniform("colour", Uniform::Float(1.0));
+        backend.set_uniform("u_flag", Uniform::Int(1));
+        backend.draw(DrawMode::Triangles, None, 3);
+        backend.set_uniform("colour", Uniform::Float(2.0));
+        backend.draw(DrawMode::Lines, None, 2);
+
+        let draws = backend.draws();
+        assert_eq!(draws.len(), 2);
+        assert_eq!(
+            draws[0].1,
+            vec![("colour", Uniform::Float(1.0)), ("u_flag", Uniform::Int(1))]
+        );
+        assert_eq!(
+            draws[1].1,
+            vec![("u_flag", Uniform::Int(1)), ("colour", Uniform::Float(2.0))]
+        );
+    }
+
+    #[test]
+    fn buffer_limit() {
+        let mut backend = RecordingBackend::new();
+        backend.buffer_limit = Some(2);
+
+        let first =
+            backend.create_buffer(BufferData::Vertices(&[0.0])).unwrap();
+        let second = backend.create_buffer(BufferData::Indices(&[0])).unwrap();
+        assert_eq!(
+            backend.create_buffer(BufferData::Vertices(&[0.0])),
+            Err(SpatioError::BufferCreation)
+        );
+        assert_eq!(backend.live_buffers(), vec![first, second]);
+
+        backend.delete_buffer(first);
+        assert_eq!(backend.live_buffers(), vec![second]);
+    }
+}
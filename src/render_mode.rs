@@ -0,0 +1,89 @@
+use crate::backend::{Backend, Face, Uniform};
+use crate::error::SpatioError;
+use crate::gpu_mesh::GpuMesh;
+use crate::material::Material;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edges {
+    // Every edge of the tessellation
+    All,
+    // Only the edges between faces that aren't in the same plane
+    HideCoplanar,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    Solid,
+    // Only the edges, in the material's colour
+    Wireframe(Edges),
+    // The faces with the edges on top, in the material's edge colour and
+    // without vertex colours or texture
+    SolidWithEdges(Edges),
+}
+
+#[allow(dead_code)]
+impl RenderMode {
+    // Which edges the mode needs uploaded, if any
+    pub fn edges(&self) -> Option<Edges> {
+        match self {
+            RenderMode::Solid => None,
+            RenderMode::Wireframe(edges) => Some(*edges),
+            RenderMode::SolidWithEdges(edges) => Some(*edges),
+        }
+    }
+}
+
+// Translucent faces are drawn in two passes, the ones facing away from the
+// camera first, so the far side of a closed mesh is blended under the near
+// side rather than in whatever order its triangles come
+fn draw_faces(
+    backend: &mut dyn Backend,
+    gpu_mesh: &GpuMesh,
+    material: &Material,
+) {
+    if !material.is_transparent() {
+        gpu_mesh.draw(backend);
+        return;
+    }
+
+    backend.set_face_culling(Some(Face::Front));
+    gpu_mesh.draw(backend);
+    backend.set_face_culling(Some(Face::Back));
+    gpu_mesh.draw(backend);
+    backend.set_face_culling(None);
+}
+
+// Draws a mesh primitive in `mode`. Expects the material to be applied, the
+// mesh bound and the matrices set.
+pub fn draw_mesh(
+    backend: &mut dyn Backend,
+    gpu_mesh: &GpuMesh,
+    material: &Material,
+    mode: RenderMode,
+) -> Result<(), SpatioError> {
+    match mode {
+        RenderMode::Solid => draw_faces(backend, gpu_mesh, material),
+        RenderMode::Wireframe(_) => gpu_mesh.draw_edges(backend),
+        RenderMode::SolidWithEdges(_) => {
+            // Pushes the faces back so the edges aren't half hidden by them
+            backend.set_depth_offset(true);
+            draw_faces(backend, gpu_mesh, material);
+            backend.set_depth_offset(false);
+
+            let program = material.program();
+            program.set_uniform(
+                backend,
+                "colour",
+                material.edge_colour.into(),
+            )?;
+            for flag in ["u_vertex_colours", "u_textured"] {
+                if program.has_uniform(flag) {
+                    program.set_uniform(backend, flag, Uniform::Int(0))?;
+                }
+            }
+            gpu_mesh.draw_edges(backend);
+        }
+    }
+
+    return Ok(());
+}
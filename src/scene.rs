@@ -0,0 +1,379 @@
+use std::rc::Rc;
+
+use crate::backend::Backend;
+use crate::camera::Camera;
+use crate::error::SpatioError;
+use crate::light::Light;
+use crate::matrix::{mat_mul, Mat4, Matrix4F, ID_MATRIX};
+use crate::primitives::Draw;
+use crate::vector::Vec3;
+
+// A node only carries a local transform; its world matrix is the product of
+// every local transform from the root down, refreshed by
+// `Scene::update_world_matrices`. Hiding a node hides its whole subtree.
+pub struct SceneNode {
+    pub id:        Option<u32>,
+    pub transform: Matrix4F,
+    pub visible:   bool,
+    pub children:  Vec<SceneNode>,
+    drawable:      Option<Rc<dyn Draw>>,
+    world:         Matrix4F,
+}
+
+pub struct Scene {
+    pub root:   SceneNode,
+    // Light every lit material in the scene
+    pub lights: Vec<Light>,
+}
+
+#[allow(dead_code)]
+impl SceneNode {
+    // Empty node, used to group children under a common transform
+    pub fn new() -> SceneNode {
+        SceneNode {
+            id:        None,
+            transform: ID_MATRIX,
+            visible:   true,
+            children:  vec![],
+            drawable:  None,
+            world:     ID_MATRIX,
+        }
+    }
+
+    pub fn with_drawable(drawable: Rc<dyn Draw>) -> SceneNode {
+        let mut node = SceneNode::new();
+        node.drawable = Some(drawable);
+        return node;
+    }
+
+    pub fn with_id(mut self, id: u32) -> SceneNode {
+        self.id = Some(id);
+        return self;
+    }
+
+    pub fn with_transform(mut self, transform: Matrix4F) -> SceneNode {
+        self.transform = transform;
+        return self;
+    }
+
+    // Returns the child so it can be filled in place
+    pub fn add_child(&mut self, child: SceneNode) -> &mut SceneNode {
+        self.children.push(child);
+        return self.children.last_mut().unwrap();
+    }
+
+    pub fn world_matrix(&self) -> Matrix4F {
+        self.world
+    }
+
+    pub fn update_world_matrices(&mut self, parent: &Matrix4F) {
+        self.world = mat_mul(parent, &self.transform);
+        for child in &mut self.children {
+            child.update_world_matrices(&self.world);
+        }
+    }
+
+    pub fn find(&self, id: u32) -> Option<&SceneNode> {
+        if self.id == Some(id) {
+            return Some(self);
+        }
+        return self.children.iter().find_map(|child| child.find(id));
+    }
+
+    pub fn find_mut(&mut self, id: u32) -> Option<&mut SceneNode> {
+        if self.id == Some(id) {
+            return Some(self);
+        }
+        return self
+            .children
+            .iter_mut()
+            .find_map(|child| child.find_mut(id));
+    }
+
+    // Depth first, parents before children, hidden subtrees skipped
+    pub fn visit(&self, visitor: &mut dyn FnMut(&SceneNode)) {
+        if !self.visible {
+            return;
+        }
+        visitor(self);
+        for child in &self.children {
+            child.visit(visitor);
+        }
+    }
+
+    // Draws the subtree in tree order, in a single pass. `Scene::draw` sorts
+    // transparent drawables instead.
+    pub fn draw(
+        &self,
+        backend: &mut dyn Backend,
+        camera: &Camera,
+        lights: &[Light],
+    ) -> Result<(), SpatioError> {
+        if !self.visible {
+            return Ok(());
+        }
+
+        if let Some(drawable) = &self.drawable {
+            drawable.draw(backend, camera, lights, Some(self.world))?;
+        }
+
+        for child in &self.children {
+            child.draw(backend, camera, lights)?;
+        }
+
+        return Ok(());
+    }
+
+    // Visible nodes with a drawable, parents before children
+    fn drawn_nodes<'a>(&'a self, nodes: &mut Vec<&'a SceneNode>) {
+        if !self.visible {
+            return;
+        }
+        if self.drawable.is_some() {
+            nodes.push(self);
+        }
+        for child in &self.children {
+            child.drawn_nodes(nodes);
+        }
+    }
+}
+
+impl Default for SceneNode {
+    fn default() -> SceneNode {
+        SceneNode::new()
+    }
+}
+
+#[allow(dead_code)]
+impl Scene {
+    // Starts with `Light::default_rig`, so lit materials show up without any
+    // setup
+    pub fn new() -> Scene {
+        Scene {
+            root:   SceneNode::new(),
+            lights: Light::default_rig(),
+        }
+    }
+
+    pub fn add(&mut self, node: SceneNode) -> &mut SceneNode {
+        self.root.add_child(node)
+    }
+
+    pub fn find(&self, id: u32) -> Option<&SceneNode> {
+        self.root.find(id)
+    }
+
+    pub fn find_mut(&mut self, id: u32) -> Option<&mut SceneNode> {
+        self.root.find_mut(id)
+    }
+
+    pub fn update_world_matrices(&mut self) {
+        self.root.update_world_matrices(&ID_MATRIX);
+    }
+
+    // Opaque drawables first, in tree order, then transparent ones from the
+    // farthest to the nearest so each is blended over what's behind it.
+    // Expects the world matrices to be up to date.
+    pub fn draw(
+        &self,
+        backend: &mut dyn Backend,
+        camera: &Camera,
+    ) -> Result<(), SpatioError> {
+        let mut nodes = vec![];
+        self.root.drawn_nodes(&mut nodes);
+
+        let (transparent, opaque): (Vec<_>, Vec<_>) =
+            nodes.into_iter().partition(|node| {
+                node.drawable.as_ref().is_some_and(|d| d.is_transparent())
+            });
+
+        let forward = camera.forward();
+        let mut transparent: Vec<(f32, &SceneNode)> = transparent
+            .into_iter()
+            .map(|node| {
+                let centre = node.drawable.as_ref().map_or(Vec3::ZERO, |d| {
+                    Mat4(node.world).transform_point(d.centre())
+                });
+                ((centre - camera.eye).dot(forward), node)
+            })
+            .collect();
+        transparent.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let sorted = transparent.into_iter().map(|(_, node)| node);
+        for node in opaque.into_iter().chain(sorted) {
+            if let Some(drawable) = &node.drawable {
+                drawable.draw(
+                    backend,
+                    camera,
+                    &self.lights,
+                    Some(node.world),
+                )?;
+            }
+        }
+
+        // Back to the defaults for whatever is drawn next
+        backend.set_blending(false);
+        backend.set_depth_write(true);
+
+        return Ok(());
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Scene {
+        Scene::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Uniform;
+    use crate::colour::Colour;
+    use crate::material::Material;
+    use crate::mesh::Mesh;
+    use crate::mesh_object::MeshObject;
+    use crate::program::Program;
+    use crate::recording::{Command, RecordingBackend};
+    use crate::transform::Transform;
+
+    fn cube(backend: &mut RecordingBackend, colour: Colour) -> Rc<MeshObject> {
+        let program = Rc::new(Program::unlit(backend).unwrap());
+        let material = Rc::new(Material::new(program, colour).unwrap());
+        let mesh = Mesh::cuboid(-Vec3::ONE * 0.5, Vec3::ONE * 0.5);
+        return Rc::new(
+            MeshObject::new(backend, mesh, Transform::default(), material)
+                .unwrap(),
+        );
+    }
+
+    fn translation(offset: Vec3) -> Matrix4F {
+        Mat4::translation(offset).0
+    }
+
+    // Where each draw put the model's origin
+    fn drawn_origins(backend: &RecordingBackend) -> Vec<Vec3> {
+        let mut origins = vec![];
+        for (_, uniforms) in backend.draws() {
+            for (name, value) in uniforms {
+                if let ("u_model", Uniform::Mat4(model)) = (name, value) {
+                    origins.push(Mat4(model).transform_point(Vec3::ZERO));
+                }
+            }
+        }
+        return origins;
+    }
+
+    fn camera() -> Camera {
+        Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO)
+    }
+
+    #[test]
+    fn world_matrices_compose_down_the_tree() {
+        let mut backend = RecordingBackend::new();
+        let drawable = cube(&mut backend, Colour::WHITE);
+
+        let mut scene = Scene::new();
+        scene
+            .add(SceneNode::new().with_transform(translation(Vec3::X)))
+            .add_child(
+                SceneNode::new().with_transform(translation(Vec3::Y * 2.0)),
+            )
+            .add_child(SceneNode::with_drawable(drawable).with_id(7));
+        scene.update_world_matrices();
+
+        let world = Mat4(scene.find(7).unwrap().world_matrix());
+        let origin = world.transform_point(Vec3::ZERO);
+        assert_eq!(origin, Vec3::new(1.0, 2.0, 0.0));
+
+        scene.draw(&mut backend, &camera()).unwrap();
+        assert_eq!(drawn_origins(&backend), vec![origin]);
+
+        // Moving a parent moves its children once the matrices are updated
+        scene.root.children[0].transform = translation(-Vec3::X);
+        scene.update_world_matrices();
+        let world = Mat4(scene.find(7).unwrap().world_matrix());
+        assert_eq!(
+            world.transform_point(Vec3::ZERO),
+            Vec3::new(-1.0, 2.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn hidden_subtrees_arent_drawn() {
+        let mut backend = RecordingBackend::new();
+        let drawable = cube(&mut backend, Colour::WHITE);
+
+        let mut scene = Scene::new();
+        let group = scene.add(SceneNode::new().with_id(1));
+        group.add_child(SceneNode::with_drawable(drawable.clone()).with_id(2));
+        group
+            .add_child(SceneNode::new())
+            .add_child(SceneNode::with_drawable(drawable).with_id(3));
+        scene.update_world_matrices();
+
+        scene.find_mut(1).unwrap().visible = false;
+        scene.draw(&mut backend, &camera()).unwrap();
+        scene.root.draw(&mut backend, &camera(), &[]).unwrap();
+        assert!(backend.draws().is_empty());
+
+        let mut visited = vec![];
+        scene.root.visit(&mut |node| visited.extend(node.id));
+        assert!(visited.is_empty());
+
+        scene.find_mut(1).unwrap().visible = true;
+        scene.find_mut(2).unwrap().visible = false;
+        scene.draw(&mut backend, &camera()).unwrap();
+        assert_eq!(backend.draws().len(), 1);
+    }
+
+    #[test]
+    fn transparent_pass_is_sorted_back_to_front() {
+        let mut backend = RecordingBackend::new();
+        let opaque = cube(&mut backend, Colour::WHITE);
+        let glass = cube(&mut backend, Colour::WHITE.with_alpha(0.5));
+
+        // Added nearest first, the opaque one last
+        let mut scene = Scene::new();
+        for (drawable, z) in [(&glass, 0.0), (&glass, -5.0), (&opaque, -10.0)] {
+            scene.add(
+                SceneNode::with_drawable(drawable.clone())
+                    .with_transform(translation(Vec3::Z * z)),
+            );
+        }
+        scene.update_world_matrices();
+        backend.commands.clear();
+        scene.draw(&mut backend, &camera()).unwrap();
+
+        // Translucent cubes are drawn twice, back faces then front faces
+        let z: Vec<f32> = drawn_origins(&backend).iter().map(|o| o.z).collect();
+        assert_eq!(z, vec![-10.0, -5.0, -5.0, 0.0, 0.0]);
+
+        // Blended without depth writes, then back to the defaults
+        let state: Vec<&Command> = backend
+            .commands
+            .iter()
+            .filter(|c| {
+                matches!(
+                    c,
+                    Command::SetBlending(_) |
+                        Command::SetDepthWrite(_) |
+                        Command::Draw { .. }
+                )
+            })
+            .collect();
+        assert_eq!(state[0], &Command::SetBlending(false));
+        assert_eq!(state[1], &Command::SetDepthWrite(true));
+        assert!(matches!(state[2], Command::Draw { .. }));
+        for pass in [&state[3..7], &state[7..11]] {
+            assert_eq!(pass[0], &Command::SetBlending(true));
+            assert_eq!(pass[1], &Command::SetDepthWrite(false));
+            assert!(matches!(pass[2], Command::Draw { .. }));
+            assert!(matches!(pass[3], Command::Draw { .. }));
+        }
+        assert_eq!(
+            &state[11..],
+            &[&Command::SetBlending(false), &Command::SetDepthWrite(true)]
+        );
+    }
+}
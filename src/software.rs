@@ -0,0 +1,905 @@
+use std::collections::HashMap;
+
+use crate::backend::{
+    Backend,
+    BufferData,
+    BufferHandle,
+    DrawMode,
+    Face,
+    ProgramHandle,
+    ProgramInfo,
+    ProgramKind,
+    TextureData,
+    TextureHandle,
+    Uniform,
+};
+use crate::colour::Colour;
+use crate::error::SpatioError;
+use crate::image::Image;
+use crate::matrix::{mat3_vec_mul, Mat4, ID_MATRIX, ID_MATRIX3};
+use crate::polyline::{
+    coverage,
+    finish_end,
+    in_dash_gap,
+    LineStyle,
+    SegmentEnd,
+};
+use crate::vector::{Vec3, Vec4};
+
+// Rasterises on the CPU into an image, so scenes can be rendered and compared
+// against reference images without a GPU or a browser. Shaders can't be run,
+// so programs are emulated by their kind. Standard, unlit and custom ones
+// transform `position` by `u_model`, `u_view` and `u_projection` and fill
+// with the `colour` uniform times the vertex colours when there are some and
+// the texture when `u_textured` is set. Standard and custom ones given lights
+// are lit by them like the standard shader. Colours and lighting are worked
+// out once per face, but textures are sampled per pixel, nearest texel, with
+// perspective correct UVs. Only RGBA bytes can be uploaded as textures.
+// Polyline programs run like the polyline shaders, with the maths in
+// `polyline`, and billboard ones place `position` in pixels around the
+// model's origin, scaled by `u_viewport`. Like the WebGL path the depth test
+// is LEQUAL.
+pub struct SoftwareRenderer {
+    target:     Framebuffer,
+    buffers:    Vec<Option<SoftwareBuffer>>,
+    textures:   Vec<Option<Image>>,
+    // Texture bound to each unit
+    units:      HashMap<u32, TextureHandle>,
+    programs:   Vec<ProgramInfo>,
+    current:    Option<usize>,
+    attributes: HashMap<String, (BufferHandle, u32)>,
+    // Per program, like in GL
+    uniforms:   Vec<HashMap<String, Uniform>>,
+}
+
+enum SoftwareBuffer {
+    Vertices(Vec<f32>),
+    Indices(Vec<u16>),
+}
+
+struct Framebuffer {
+    width:        u32,
+    height:       u32,
+    colour:       Vec<Colour>,
+    depth:        Vec<f32>,
+    blending:     bool,
+    depth_write:  bool,
+    depth_offset: bool,
+    depth_test:   bool,
+    culled_face:  Option<Face>,
+}
+
+// Added to the depth of triangles while the depth offset is on, a unit of a
+// 16 bit depth buffer
+const DEPTH_OFFSET: f32 = 1.0 / 65536.0;
+
+// What the polyline vertex shader works out for a corner of a segment
+struct LineCorner {
+    clip:        Vec4,
+    // Along and across the segment in pixels, and 1, all times w, then the
+    // distance along the line
+    local:       Vec4,
+    length:      f32,
+    half_widths: [f32; 2],
+    ends:        [SegmentEnd; 2],
+}
+
+// Signed area of the parallelogram a, b, p. Positive when p is on the left of
+// a -> b in screen space, y going down.
+fn edge(a: Vec3, b: Vec3, p: Vec3) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+// Source over destination by the source's alpha, like the WebGL backend's
+// blend function
+fn blend(source: Colour, destination: Colour) -> Colour {
+    let a = source.a.clamp(0.0, 1.0);
+    Colour {
+        r: source.r * a + destination.r * (1.0 - a),
+        g: source.g * a + destination.g * (1.0 - a),
+        b: source.b * a + destination.b * (1.0 - a),
+        a: a + destination.a * (1.0 - a),
+    }
+}
+
+// Nearest texel at `uv`, with v going up from the bottom row like in GL
+fn sample(texture: &Image, uv: Vec4) -> Colour {
+    let texel = |coordinate: f32, size: u32| {
+        let coordinate = (coordinate.clamp(0.0, 1.0) * size as f32) as u32;
+        coordinate.min(size - 1)
+    };
+    let x = texel(uv.x, texture.width);
+    let y = texel(1.0 - uv.y, texture.height);
+
+    let [r, g, b, a] = texture.get(x, y);
+    return Colour::from_rgba_u8(r, g, b, a);
+}
+
+fn modulate(colour: Colour, other: Colour) -> Colour {
+    Colour {
+        r: colour.r * other.r,
+        g: colour.g * other.g,
+        b: colour.b * other.b,
+        a: colour.a * other.a,
+    }
+}
+
+// Clips a clip space polygon against the near plane (z >= -w). Each corner
+// carries its varyings along.
+fn clip_near(polygon: &[(Vec4, Vec4)]) -> Vec<(Vec4, Vec4)> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+
+    for i in 0..polygon.len() {
+        let current = polygon[i];
+        let next = polygon[(i + 1) % polygon.len()];
+        let current_distance = current.0.z + current.0.w;
+        let next_distance = next.0.z + next.0.w;
+
+        if current_distance >= 0.0 {
+            clipped.push(current);
+        }
+        // A segment only has one edge to cross
+        if polygon.len() == 2 && i == 1 {
+            break;
+        }
+        if (current_distance >= 0.0) != (next_distance >= 0.0) {
+            let t = current_distance / (current_distance - next_distance);
+            clipped
+                .push((current.0.lerp(next.0, t), current.1.lerp(next.1, t)));
+        }
+    }
+
+    return clipped;
+}
+
+impl Framebuffer {
+    // Window coordinates, with depth remapped to 0..1 like gl_FragCoord
+    fn window(&self, clip: Vec4) -> Vec3 {
+        let ndc = clip.perspective_divide();
+        Vec3::new(
+            (ndc.x + 1.0) / 2.0 * self.width as f32,
+            (1.0 - ndc.y) / 2.0 * self.height as f32,
+            (ndc.z + 1.0) / 2.0,
+        )
+    }
+
+    fn plot(&mut self, x: u32, y: u32, depth: f32, colour: Colour) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        if !(0.0..=1.0).contains(&depth) {
+            return;
+        }
+
+        let i = y as usize * self.width as usize + x as usize;
+        if self.depth_test && depth > self.depth[i] {
+            return;
+        }
+
+        if self.depth_write {
+            self.depth[i] = depth;
+        }
+        self.colour[i] = if self.blending {
+            blend(colour, self.colour[i])
+        } else {
+            colour
+        };
+    }
+
+    // `shade` gives the colour of a pixel from its varyings, interpolated
+    // with perspective correction, or None to discard it
+    fn fill(
+        &mut self,
+        triangle: [(Vec4, Vec4); 3],
+        shade: impl Fn(Vec4) -> Option<Colour>,
+    ) {
+        let [a, b, c] = triangle.map(|(corner, _)| self.window(corner));
+        let [wa_clip, wb_clip, wc_clip] = triangle.map(|(corner, _)| corner.w);
+        let [uv_a, uv_b, uv_c] = triangle.map(|(_, varyings)| varyings);
+
+        let area = edge(a, b, c);
+        if area == 0.0 || !area.is_finite() {
+            return;
+        }
+        // With y going down, counter-clockwise on screen is a negative area
+        let face = if area < 0.0 { Face::Front } else { Face::Back };
+        if self.culled_face == Some(face) {
+            return;
+        }
+
+        let (width, height) = (self.width as f32, self.height as f32);
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as u32;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as u32;
+        let max_x = a.x.max(b.x).max(c.x).ceil().min(width) as u32;
+        let max_y = a.y.max(b.y).max(c.y).ceil().min(height) as u32;
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
+
+                // Barycentric weights, whatever the winding
+                let wa = edge(b, c, p) / area;
+                let wb = edge(c, a, p) / area;
+                let wc = edge(a, b, p) / area;
+                if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                    continue;
+                }
+
+                let mut depth = wa * a.z + wb * b.z + wc * c.z;
+                if self.depth_offset {
+                    depth += DEPTH_OFFSET;
+                }
+
+                // Varyings are linear in clip space, not on screen
+                let (pa, pb, pc) = (wa / wa_clip, wb / wb_clip, wc / wc_clip);
+                let varyings =
+                    (uv_a * pa + uv_b * pb + uv_c * pc) / (pa + pb + pc);
+                if let Some(colour) = shade(varyings) {
+                    self.plot(x, y, depth, colour);
+                }
+            }
+        }
+    }
+
+    fn line(&mut self, segment: [Vec4; 2], colour: Colour) {
+        let [a, b] = segment.map(|end| self.window(end));
+
+        let steps = (b.x - a.x).abs().max((b.y - a.y).abs()).ceil();
+        if !steps.is_finite() {
+            return;
+        }
+        let steps = steps.max(1.0) as u32;
+
+        for step in 0..=steps {
+            let point = a.lerp(b, step as f32 / steps as f32);
+            if point.x < 0.0 || point.y < 0.0 {
+                continue;
+            }
+            self.plot(point.x as u32, point.y as u32, point.z, colour);
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl SoftwareRenderer {
+    pub fn new(width: u32, height: u32) -> SoftwareRenderer {
+        let size = width as usize * height as usize;
+
+        SoftwareRenderer {
+            target:     Framebuffer {
+                width,
+                height,
+                colour: vec![Colour::BLACK; size],
+                depth: vec![1.0; size],
+                blending: false,
+                depth_write: true,
+                depth_offset: false,
+                depth_test: true,
+                culled_face: None,
+            },
+            buffers:    vec![],
+            textures:   vec![],
+            units:      HashMap::new(),
+            programs:   vec![],
+            current:    None,
+            attributes: HashMap::new(),
+            uniforms:   vec![],
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.target.width, self.target.height)
+    }
+
+    pub fn image(&self) -> Image {
+        let channel =
+            |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        return Image {
+            width:  self.target.width,
+            height: self.target.height,
+            pixels: self
+                .target
+                .colour
+                .iter()
+                .flat_map(|c| {
+                    [channel(c.r), channel(c.g), channel(c.b), channel(c.a)]
+                })
+                .collect(),
+        };
+    }
+
+    fn uniform(&self, name: &str) -> Option<&Uniform> {
+        self.uniforms.get(self.current?)?.get(name)
+    }
+
+    fn matrix_uniform(&self, name: &str) -> Mat4 {
+        match self.uniform(name) {
+            Some(Uniform::Mat4(matrix)) => Mat4(*matrix),
+            _ => Mat4(ID_MATRIX),
+        }
+    }
+
+    fn vec3_uniform(&self, name: &str) -> Vec3 {
+        match self.uniform(name) {
+            Some(Uniform::Vec3(value)) => *value,
+            _ => Vec3::ZERO,
+        }
+    }
+
+    fn float_uniform(&self, name: &str) -> f32 {
+        match self.uniform(name) {
+            Some(Uniform::Float(value)) => *value,
+            Some(Uniform::Int(value)) => *value as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn colour_uniform(&self) -> Colour {
+        match self.uniform("colour") {
+            Some(Uniform::Vec4([r, g, b, a])) => Colour {
+                r: *r,
+                g: *g,
+                b: *b,
+                a: *a,
+            },
+            _ => Colour::WHITE,
+        }
+    }
+
+    // The texture the program samples when it's told to use one and there is
+    // one bound to its unit
+    fn texture(&self) -> Option<&Image> {
+        if !matches!(self.uniform("u_textured"), Some(Uniform::Int(1))) {
+            return None;
+        }
+        let unit = match self.uniform("u_texture") {
+            Some(Uniform::Int(unit)) => *unit as u32,
+            _ => 0,
+        };
+        let texture = self.units.get(&unit)?;
+        return self.textures.get(texture.0 as usize)?.as_ref();
+    }
+
+    // Per-vertex colours when the program is told to use them and they are
+    // bound, None otherwise
+    fn vertex_colours(&self) -> Option<Vec<Colour>> {
+        if !matches!(self.uniform("u_vertex_colours"), Some(Uniform::Int(1))) {
+            return None;
+        }
+        let (buffer, 4) = self.attributes.get("vertex_colour")? else {
+            return None;
+        };
+        let Some(Some(SoftwareBuffer::Vertices(data))) =
+            self.buffers.get(buffer.0 as usize)
+        else {
+            return None;
+        };
+
+        return Some(
+            data.chunks_exact(4)
+                .map(|c| Colour::from_rgba_f32(c[0], c[1], c[2], c[3]))
+                .collect(),
+        );
+    }
+
+    // Three components of an attribute for each vertex, None when it isn't
+    // bound to a vertex buffer
+    fn attribute(&self, name: &str) -> Option<Vec<Vec3>> {
+        let (buffer, size) = self.attributes.get(name)?;
+        let Some(Some(SoftwareBuffer::Vertices(data))) =
+            self.buffers.get(buffer.0 as usize)
+        else {
+            return None;
+        };
+
+        let size = *size as usize;
+        return Some(
+            data.chunks_exact(size.max(1))
+                .map(|v| {
+                    let component = |i: usize| v.get(i).copied().unwrap_or(0.0);
+                    Vec3::new(component(0), component(1), component(2))
+                })
+                .collect(),
+        );
+    }
+
+    // What the polyline vertex shader does for a corner, given its
+    // `position`, `other`, `beyond`, `beyond_other`, `corner` and
+    // `line_distance` attributes
+    fn line_corner(
+        &self,
+        style: &LineStyle,
+        matrix: Mat4,
+        viewport: [f32; 2],
+        attributes: [Vec3; 6],
+    ) -> LineCorner {
+        let [position, other, beyond, beyond_other, corner, distance] =
+            attributes;
+        let at_end = corner.y > 0.5;
+        let side = corner.x;
+
+        let screen = |clip: Vec4| {
+            Vec3::new(
+                clip.x / clip.w * viewport[0] * 0.5,
+                clip.y / clip.w * viewport[1] * 0.5,
+                0.0,
+            )
+        };
+        let focal = self.matrix_uniform("u_projection").0[5];
+        let half_width =
+            |clip: Vec4| style.width.half_pixels(focal, viewport[1], clip.w);
+
+        let clip = matrix * position.extend(1.0);
+        let other_clip = matrix * other.extend(1.0);
+        let (start_clip, end_clip, before, after) = if at_end {
+            (other_clip, clip, beyond_other, beyond)
+        } else {
+            (clip, other_clip, beyond, beyond_other)
+        };
+        let start = screen(start_clip);
+        let end = screen(end_clip);
+        let before = screen(matrix * before.extend(1.0));
+        let after = screen(matrix * after.extend(1.0));
+
+        let length = (end - start).length();
+        let direction = if length > 0.0 {
+            (end - start) / length
+        } else {
+            Vec3::X
+        };
+        let half_widths = [half_width(start_clip), half_width(end_clip)];
+
+        let (start_end, start_offset) = finish_end(
+            style,
+            start,
+            before,
+            false,
+            direction,
+            half_widths[0],
+            side,
+        );
+        let (end_end, end_offset) = finish_end(
+            style,
+            end,
+            after,
+            true,
+            direction,
+            half_widths[1],
+            side,
+        );
+        let offset = if at_end { end_offset } else { start_offset };
+
+        let along = offset.dot(direction) + if at_end { length } else { 0.0 };
+        let across = offset.dot(Vec3::new(-direction.y, direction.x, 0.0));
+
+        return LineCorner {
+            clip: clip +
+                Vec4::new(
+                    offset.x * 2.0 / viewport[0] * clip.w,
+                    offset.y * 2.0 / viewport[1] * clip.w,
+                    0.0,
+                    0.0,
+                ),
+            local: Vec4::new(
+                along * clip.w,
+                across * clip.w,
+                clip.w,
+                distance.x,
+            ),
+            length,
+            half_widths,
+            ends: [start_end, end_end],
+        };
+    }
+
+    // Polylines are drawn as triangles, each corner placed by `line_corner`
+    // and each pixel covered as `polyline::coverage` says
+    fn draw_polyline(&mut self, count: usize) {
+        let names = [
+            "position",
+            "other",
+            "beyond",
+            "beyond_other",
+            "corner",
+            "line_distance",
+        ];
+        let mut attributes = Vec::with_capacity(names.len());
+        for name in names {
+            match self.attribute(name) {
+                Some(values) if values.len() >= count => {
+                    attributes.push(values)
+                }
+                _ => return,
+            }
+        }
+        let Some(Uniform::Vec2(viewport)) = self.uniform("u_viewport") else {
+            return;
+        };
+        let viewport = *viewport;
+
+        let matrix = self.matrix_uniform("u_projection") *
+            self.matrix_uniform("u_view") *
+            self.matrix_uniform("u_model");
+        let colour = self.colour_uniform();
+        let Some(style) =
+            LineStyle::from_uniforms(|name| self.uniform(name).copied())
+        else {
+            return;
+        };
+
+        let corners: Vec<LineCorner> = (0..count)
+            .map(|i| {
+                let attribute = |a: usize| attributes[a][i];
+                self.line_corner(
+                    &style,
+                    matrix,
+                    viewport,
+                    [0, 1, 2, 3, 4, 5].map(attribute),
+                )
+            })
+            .collect();
+
+        for triangle in corners.chunks_exact(3) {
+            // Every corner of a segment agrees on its length, widths and
+            // modes
+            let segment = &triangle[0];
+            let shade = |local: Vec4| {
+                if in_dash_gap(local.w, style.dash) {
+                    return None;
+                }
+                let coverage = coverage(
+                    local.x / local.z,
+                    local.y / local.z,
+                    segment.length,
+                    segment.half_widths,
+                    segment.ends,
+                );
+                (coverage > 0.0).then(|| colour.with_alpha(colour.a * coverage))
+            };
+
+            let polygon: Vec<(Vec4, Vec4)> = clip_near(
+                &triangle
+                    .iter()
+                    .map(|corner| (corner.clip, corner.local))
+                    .collect::<Vec<_>>(),
+            );
+            for i in 1..polygon.len().saturating_sub(1) {
+                self.target
+                    .fill([polygon[0], polygon[i], polygon[i + 1]], shade);
+            }
+        }
+    }
+
+    // What the standard fragment shader does, with the face's world space
+    // normal and centre standing in for the interpolated ones: ambient,
+    // Lambert diffuse and Phong specular lighting
+    fn shade(&self, colour: Colour, normal: Vec3, position: Vec3) -> Colour {
+        let Some(Uniform::Int(count)) = self.uniform("u_light_count") else {
+            return colour;
+        };
+        let specular = self.float_uniform("u_specular");
+        let shininess = self.float_uniform("u_shininess");
+
+        // Faces are lit from the side the camera sees
+        let to_camera =
+            (self.vec3_uniform("u_camera_position") - position).normalize();
+        let normal = if normal.dot(to_camera) < 0.0 {
+            -normal
+        } else {
+            normal
+        };
+
+        let mut diffuse_light = self.vec3_uniform("u_ambient");
+        let mut specular_light = Vec3::ZERO;
+        for i in 0..*count {
+            let name = format!("u_light_vectors[{}]", i);
+            let Some(Uniform::Vec4([x, y, z, w])) = self.uniform(&name) else {
+                continue;
+            };
+            // w is 0 for a direction, 1 for a position
+            let vector = Vec3::new(*x, *y, *z);
+            let to_light = if *w == 0.0 {
+                -vector
+            } else {
+                vector - position
+            }
+            .normalize();
+            let light_colour =
+                self.vec3_uniform(&format!("u_light_colours[{}]", i));
+
+            let diffuse = normal.dot(to_light).max(0.0);
+            diffuse_light += light_colour * diffuse;
+            if diffuse > 0.0 {
+                let reflected = normal * (2.0 * diffuse) - to_light;
+                let highlight =
+                    reflected.dot(to_camera).max(0.0).powf(shininess);
+                specular_light += light_colour * (specular * highlight);
+            }
+        }
+
+        return Colour {
+            r: colour.r * diffuse_light.x + specular_light.x,
+            g: colour.g * diffuse_light.y + specular_light.y,
+            b: colour.b * diffuse_light.z + specular_light.z,
+            a: colour.a,
+        };
+    }
+}
+
+impl Backend for SoftwareRenderer {
+    fn create_buffer(
+        &mut self,
+        data: BufferData,
+    ) -> Result<BufferHandle, SpatioError> {
+        self.buffers.push(Some(match data {
+            BufferData::Vertices(data) => {
+                SoftwareBuffer::Vertices(data.to_vec())
+            }
+            BufferData::Indices(data) => SoftwareBuffer::Indices(data.to_vec()),
+        }));
+        return Ok(BufferHandle(self.buffers.len() as u32 - 1));
+    }
+
+    fn delete_buffer(&mut self, buffer: BufferHandle) {
+        if let Some(slot) = self.buffers.get_mut(buffer.0 as usize) {
+            *slot = None;
+        }
+    }
+
+    fn create_texture(
+        &mut self,
+        data: TextureData,
+    ) -> Result<TextureHandle, SpatioError> {
+        data.check()?;
+
+        let image = match data {
+            TextureData::Rgba {
+                width,
+                height,
+                pixels,
+            } => Image {
+                width,
+                height,
+                pixels: pixels.to_vec(),
+            },
+            #[cfg(feature = "web")]
+            _ => {
+                return Err(SpatioError::TextureCreation(String::from(
+                    "Only RGBA bytes can be drawn in software",
+                )))
+            }
+        };
+
+        self.textures.push(Some(image));
+        return Ok(TextureHandle(self.textures.len() as u32 - 1));
+    }
+
+    fn delete_texture(&mut self, texture: TextureHandle) {
+        if let Some(slot) = self.textures.get_mut(texture.0 as usize) {
+            *slot = None;
+        }
+    }
+
+    fn bind_texture(&mut self, unit: u32, texture: TextureHandle) {
+        self.units.insert(unit, texture);
+    }
+
+    // The sources are only read for what they declare
+    fn create_program(
+        &mut self,
+        kind: ProgramKind,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<ProgramHandle, SpatioError> {
+        self.programs.push(ProgramInfo::from_source(
+            kind,
+            vertex_source,
+            fragment_source,
+        ));
+        self.uniforms.push(HashMap::new());
+        return Ok(ProgramHandle(self.programs.len() as u32 - 1));
+    }
+
+    fn use_program(&mut self, program: ProgramHandle) {
+        if (program.0 as usize) < self.programs.len() {
+            self.current = Some(program.0 as usize);
+        }
+    }
+
+    fn program_info(&self, program: ProgramHandle) -> Option<ProgramInfo> {
+        self.programs.get(program.0 as usize).cloned()
+    }
+
+    fn bind_attribute(&mut self, name: &str, buffer: BufferHandle, size: u32) {
+        self.attributes.insert(name.to_string(), (buffer, size));
+    }
+
+    fn disable_attribute(&mut self, name: &str) {
+        self.attributes.remove(name);
+    }
+
+    fn set_uniform(&mut self, name: &str, value: Uniform) {
+        let current = self.current;
+        if let Some(uniforms) = current.and_then(|i| self.uniforms.get_mut(i)) {
+            uniforms.insert(name.to_string(), value);
+        }
+    }
+
+    fn draw(
+        &mut self,
+        mode: DrawMode,
+        indices: Option<BufferHandle>,
+        count: usize,
+    ) {
+        let Some(kind) = self.current.map(|i| self.programs[i].kind) else {
+            return;
+        };
+        if kind == ProgramKind::Polyline {
+            if mode == DrawMode::Triangles && indices.is_none() {
+                self.draw_polyline(count);
+            }
+            return;
+        }
+
+        let Some(positions) = self.attribute("position") else {
+            return;
+        };
+        let normals = self.attribute("normal");
+        let uvs = self
+            .attribute("uv")
+            .filter(|uvs| uvs.len() == positions.len());
+        // Copied out, the framebuffer is written while it's read
+        let texture = self.texture().cloned();
+
+        let order: Vec<usize> = match indices {
+            Some(buffer) => match self.buffers.get(buffer.0 as usize) {
+                Some(Some(SoftwareBuffer::Indices(data))) => {
+                    data.iter().take(count).map(|&i| i as usize).collect()
+                }
+                _ => return,
+            },
+            None => (0..count).collect(),
+        };
+        if order.iter().any(|&i| i >= positions.len()) {
+            return;
+        }
+        let vertex_colours = self
+            .vertex_colours()
+            .filter(|colours| colours.len() == positions.len());
+
+        let model = self.matrix_uniform("u_model");
+        let clip_matrix = self.matrix_uniform("u_projection") *
+            self.matrix_uniform("u_view") *
+            model;
+        let normal_matrix = match self.uniform("u_normal_matrix") {
+            Some(Uniform::Mat3(matrix)) => *matrix,
+            _ => model.normal_matrix().unwrap_or(ID_MATRIX3),
+        };
+        let uniform_colour = self.colour_uniform();
+        let lit = matches!(kind, ProgramKind::Standard | ProgramKind::Custom);
+        let viewport = match (kind, self.uniform("u_viewport")) {
+            (ProgramKind::Billboard, Some(Uniform::Vec2(viewport))) => {
+                Some(*viewport)
+            }
+            (ProgramKind::Billboard, _) => return,
+            _ => None,
+        };
+        let clip = |i: usize| {
+            let uv = uvs.as_ref().map_or(Vec4::ZERO, |uvs| uvs[i].extend(0.0));
+            let position = match viewport {
+                Some([width, height]) => {
+                    let anchor = clip_matrix * Vec4::new(0.0, 0.0, 0.0, 1.0);
+                    let offset = positions[i];
+                    anchor +
+                        Vec4::new(
+                            offset.x * 2.0 / width * anchor.w,
+                            offset.y * 2.0 / height * anchor.w,
+                            0.0,
+                            0.0,
+                        )
+                }
+                None => clip_matrix * positions[i].extend(1.0),
+            };
+            (position, uv)
+        };
+        // Vertex colours are averaged, like the normals
+        let colour = |vertices: &[usize]| match &vertex_colours {
+            Some(colours) => {
+                let n = vertices.len() as f32;
+                let sum = vertices.iter().fold([0.0; 4], |sum, &i| {
+                    let c = colours[i];
+                    [sum[0] + c.r, sum[1] + c.g, sum[2] + c.b, sum[3] + c.a]
+                });
+                Colour::from_rgba_f32(
+                    uniform_colour.r * sum[0] / n,
+                    uniform_colour.g * sum[1] / n,
+                    uniform_colour.b * sum[2] / n,
+                    uniform_colour.a * sum[3] / n,
+                )
+            }
+            None => uniform_colour,
+        };
+
+        match mode {
+            DrawMode::Triangles => {
+                for triangle in order.chunks_exact(3) {
+                    let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+
+                    // Flat shading, from the vertex normals when there are
+                    // some and the winding otherwise
+                    let normal = match &normals {
+                        Some(n) if n.len() == positions.len() => {
+                            n[a] + n[b] + n[c]
+                        }
+                        _ => (positions[b] - positions[a])
+                            .cross(positions[c] - positions[a]),
+                    };
+                    let normal =
+                        mat3_vec_mul(&normal_matrix, normal).normalize();
+                    let centre =
+                        (positions[a] + positions[b] + positions[c]) / 3.0;
+                    let centre = (model * centre.extend(1.0)).xyz();
+                    let colour = if lit {
+                        self.shade(colour(triangle), normal, centre)
+                    } else {
+                        colour(triangle)
+                    };
+
+                    let shade = |uv: Vec4| match (&texture, &uvs) {
+                        (Some(texture), Some(_)) => {
+                            Some(modulate(colour, sample(texture, uv)))
+                        }
+                        _ => Some(colour),
+                    };
+
+                    let polygon = clip_near(&[clip(a), clip(b), clip(c)]);
+                    for i in 1..polygon.len().saturating_sub(1) {
+                        self.target.fill(
+                            [polygon[0], polygon[i], polygon[i + 1]],
+                            shade,
+                        );
+                    }
+                }
+            }
+            DrawMode::Lines => {
+                for segment in order.chunks_exact(2) {
+                    let clipped =
+                        clip_near(&[clip(segment[0]), clip(segment[1])]);
+                    if let [(a, _), (b, _)] = clipped[..] {
+                        self.target.line([a, b], colour(segment));
+                    }
+                }
+            }
+        }
+    }
+
+    // The image size is fixed at creation
+    fn set_viewport(&mut self, _width: u32, _height: u32) {}
+
+    fn set_blending(&mut self, enabled: bool) {
+        self.target.blending = enabled;
+    }
+
+    fn set_depth_write(&mut self, enabled: bool) {
+        self.target.depth_write = enabled;
+    }
+
+    fn set_depth_offset(&mut self, enabled: bool) {
+        self.target.depth_offset = enabled;
+    }
+
+    fn set_depth_test(&mut self, enabled: bool) {
+        self.target.depth_test = enabled;
+    }
+
+    fn set_face_culling(&mut self, face: Option<Face>) {
+        self.target.culled_face = face;
+    }
+
+    fn clear(&mut self, colour: Colour) {
+        self.target.colour.fill(colour);
+        self.target.depth.fill(1.0);
+        self.target.depth_write = true;
+    }
+}
@@ -0,0 +1,110 @@
+#[cfg(feature = "web")]
+use web_sys::{HtmlCanvasElement, HtmlImageElement};
+
+use crate::backend::{Backend, TextureData, TextureHandle};
+use crate::error::SpatioError;
+use crate::image::Image;
+
+// An image uploaded to a backend, sampled through the UVs of the meshes drawn
+// with a textured `Material`. Like `GpuMesh` it has to be deleted
+// explicitly.
+pub struct Texture {
+    handle: TextureHandle,
+    width:  u32,
+    height: u32,
+    // Whether every pixel is known to have full alpha. Only bytes can be
+    // checked, images and canvases are assumed to have transparent parts.
+    opaque: bool,
+}
+
+#[allow(dead_code)]
+impl Texture {
+    pub fn new(
+        backend: &mut dyn Backend,
+        data: TextureData,
+    ) -> Result<Texture, SpatioError> {
+        let (width, height) = data.size();
+        let opaque = match data {
+            TextureData::Rgba {
+                pixels, ..
+            } => pixels.chunks_exact(4).all(|pixel| pixel[3] == u8::MAX),
+            #[cfg(feature = "web")]
+            _ => false,
+        };
+        let handle = backend.create_texture(data)?;
+
+        return Ok(Texture {
+            handle,
+            width,
+            height,
+            opaque,
+        });
+    }
+
+    // 4 bytes per pixel, rows from top to bottom
+    pub fn from_rgba(
+        backend: &mut dyn Backend,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<Texture, SpatioError> {
+        return Texture::new(
+            backend,
+            TextureData::Rgba {
+                width,
+                height,
+                pixels,
+            },
+        );
+    }
+
+    pub fn from_image(
+        backend: &mut dyn Backend,
+        image: &Image,
+    ) -> Result<Texture, SpatioError> {
+        return Texture::new(backend, image.into());
+    }
+
+    // The image has to be loaded, its natural size is used
+    #[cfg(feature = "web")]
+    pub fn from_html_image(
+        backend: &mut dyn Backend,
+        image: &HtmlImageElement,
+    ) -> Result<Texture, SpatioError> {
+        if !image.complete() {
+            return Err(SpatioError::TextureCreation(String::from(
+                "Image isn't loaded yet",
+            )));
+        }
+        return Texture::new(backend, TextureData::HtmlImage(image));
+    }
+
+    // Copies what's currently drawn on the canvas
+    #[cfg(feature = "web")]
+    pub fn from_canvas(
+        backend: &mut dyn Backend,
+        canvas: &HtmlCanvasElement,
+    ) -> Result<Texture, SpatioError> {
+        return Texture::new(backend, TextureData::Canvas(canvas));
+    }
+
+    pub fn handle(&self) -> TextureHandle {
+        self.handle
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.opaque
+    }
+
+    pub fn bind(&self, backend: &mut dyn Backend, unit: u32) {
+        backend.bind_texture(unit, self.handle);
+    }
+
+    pub fn delete(self, backend: &mut dyn Backend) {
+        backend.delete_texture(self.handle);
+    }
+}
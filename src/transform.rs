@@ -0,0 +1,60 @@
+use crate::matrix::{
+    mat_mul_many,
+    scale_matrix,
+    translate_matrix,
+    Matrix4F,
+    Quaternion,
+};
+use crate::vector::Vec3;
+
+// Placement of a single object: scaled first, then rotated, then translated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation:    Quaternion,
+    pub scale:       Vec3,
+}
+
+#[allow(dead_code)]
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        translation: Vec3::ZERO,
+        rotation:    Quaternion::IDENTITY,
+        scale:       Vec3::ONE,
+    };
+
+    pub fn from_translation(translation: Vec3) -> Transform {
+        Transform {
+            translation,
+            ..Transform::IDENTITY
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Quaternion) -> Transform {
+        self.rotation = rotation;
+        return self;
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Transform {
+        self.scale = scale;
+        return self;
+    }
+
+    pub fn matrix(&self) -> Matrix4F {
+        mat_mul_many(&[
+            translate_matrix(
+                self.translation.x,
+                self.translation.y,
+                self.translation.z,
+            ),
+            self.rotation.to_matrix(),
+            scale_matrix(self.scale.x, self.scale.y, self.scale.z),
+        ])
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::IDENTITY
+    }
+}
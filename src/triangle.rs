@@ -1,106 +1,98 @@
-use web_sys::{
-    WebGl2RenderingContext,
-    WebGlBuffer,
-    WebGlProgram,
-    WebGlUniformLocation,
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+use crate::backend::Backend;
+use crate::camera::Camera;
+use crate::error::SpatioError;
+use crate::light::Light;
+use crate::material::Material;
+use crate::matrix::Matrix4F;
+use crate::mesh::Mesh;
+use crate::mesh_object::MeshObject;
+use crate::picking::{MeshHit, Pickable};
+use crate::primitives::Draw;
+use crate::ray::{
+    intersect_transformed,
+    intersect_triangle,
+    Intersect,
+    Ray,
+    RayHit,
 };
+use crate::transform::Transform;
+use crate::vector::{Vec3, Vertex};
 
-use crate::colour::Colour;
-use crate::primitives::{Draw, Vertex};
-use crate::Matrix4F;
-
-pub struct Triangle<'a> {
-    positions_buffer:            WebGlBuffer,
-    colour:                      Colour,
-    position_attribute_location: i32,
-    colour_uniform_location:     WebGlUniformLocation,
-    program:                     &'a WebGlProgram,
+pub struct Triangle {
+    object: MeshObject,
 }
 
 #[allow(dead_code)]
-impl<'a> Triangle<'a> {
+impl Triangle {
     pub fn new(
-        context: &WebGl2RenderingContext,
+        backend: &mut dyn Backend,
         a: Vertex,
         b: Vertex,
         c: Vertex,
-        colour: Colour,
-        program: &'a WebGlProgram,
-    ) -> Triangle<'a> {
-        let vertices = [a.x, a.y, a.z, b.x, b.y, b.z, c.x, c.y, c.z];
-
-        let positions_buffer = context.create_buffer().unwrap();
-
-        context.bind_buffer(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            Some(&positions_buffer),
-        );
-
-        unsafe {
-            let positions_array_buf_view =
-                js_sys::Float32Array::view(&vertices);
-
-            context.buffer_data_with_array_buffer_view(
-                WebGl2RenderingContext::ARRAY_BUFFER,
-                &positions_array_buf_view,
-                WebGl2RenderingContext::STATIC_DRAW,
-            );
-        }
-
-        let position_attribute_location =
-            context.get_attrib_location(&program, "position");
+        material: Rc<Material>,
+    ) -> Result<Triangle, SpatioError> {
+        let object = MeshObject::new(
+            backend,
+            Mesh::triangle(Vec3::from(a), Vec3::from(b), Vec3::from(c)),
+            Transform::IDENTITY,
+            material,
+        )?;
+
+        return Ok(Triangle {
+            object,
+        });
+    }
+}
 
-        let colour_uniform_location = context
-            .get_uniform_location(&program, "colour")
-            .expect("Missing \"colour\" uniform in program");
+// For the transform, render mode and vertex colours
+impl Deref for Triangle {
+    type Target = MeshObject;
 
+    fn deref(&self) -> &MeshObject {
+        &self.object
+    }
+}
 
-        Triangle {
-            positions_buffer,
-            colour,
-            position_attribute_location,
-            colour_uniform_location,
-            program,
-        }
+impl DerefMut for Triangle {
+    fn deref_mut(&mut self) -> &mut MeshObject {
+        &mut self.object
     }
 }
 
-impl<'a> Draw for Triangle<'a> {
+impl Draw for Triangle {
     fn draw(
         &self,
-        context: &web_sys::WebGl2RenderingContext,
-        _uniform_matrix: Option<Matrix4F>,
-    ) -> Result<(), String> {
-        context.bind_buffer(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            Some(&self.positions_buffer),
-        );
-
-        context.enable_vertex_attrib_array(
-            self.position_attribute_location as u32,
-        );
-
-        context.vertex_attrib_pointer_with_i32(
-            self.position_attribute_location as u32,
-            3,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            0,
-            0,
-        );
+        backend: &mut dyn Backend,
+        camera: &Camera,
+        lights: &[Light],
+        parent_matrix: Option<Matrix4F>,
+    ) -> Result<(), SpatioError> {
+        self.object.draw(backend, camera, lights, parent_matrix)
+    }
 
-        context.use_program(Some(self.program));
+    fn is_transparent(&self) -> bool {
+        self.object.is_transparent()
+    }
 
-        context.uniform4f(
-            Some(&self.colour_uniform_location),
-            self.colour.r,
-            self.colour.g,
-            self.colour.b,
-            self.colour.a,
-        );
+    fn centre(&self) -> Vec3 {
+        self.object.centre()
+    }
+}
 
-        context.draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, 3);
+impl Intersect for Triangle {
+    fn intersect(&self, ray: &Ray) -> Option<RayHit> {
+        let [a, b, c] = [0, 1, 2].map(|i| self.mesh().position(i));
+        intersect_transformed(ray, &self.transform.matrix(), |ray| {
+            intersect_triangle(ray, a, b, c)
+        })
+    }
+}
 
-        return Ok(());
+impl Pickable for Triangle {
+    fn pick(&self, ray: &Ray) -> Option<MeshHit> {
+        self.object.pick(ray)
     }
 }
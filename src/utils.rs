@@ -6,97 +6,102 @@ use web_sys::{
     HtmlCanvasElement,
     HtmlInputElement,
     InputEvent,
-    WebGl2RenderingContext,
-    WebGlProgram,
-    WebGlShader,
+    MouseEvent,
 };
 
-pub fn compile_shader(
-    context: &WebGl2RenderingContext,
-    shader_type: u32,
-    source: &str,
-) -> Result<WebGlShader, String> {
-    let shader = context
-        .create_shader(shader_type)
-        .ok_or_else(|| String::from("Unable to create shader object"))?;
-    context.shader_source(&shader, source);
-    context.compile_shader(&shader);
-
-    if context
-        .get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
-        return Ok(shader);
-    }
+use crate::error::SpatioError;
 
-    return Err(context
-        .get_shader_info_log(&shader)
-        .unwrap_or_else(|| String::from("Unknown error creating shader")));
+pub fn request_animation_frame(f: &Closure<dyn FnMut(f64)>) {
+    window()
+        .unwrap()
+        .request_animation_frame(f.as_ref().unchecked_ref())
+        .unwrap();
 }
 
-pub fn link_program(
-    context: &WebGl2RenderingContext,
-    vert_shader: &WebGlShader,
-    frag_shader: &WebGlShader,
-) -> Result<WebGlProgram, String> {
-    let program = context
-        .create_program()
-        .ok_or_else(|| String::from("Unable to create program"))?;
-    context.attach_shader(&program, &vert_shader);
-    context.attach_shader(&program, &frag_shader);
-    context.link_program(&program);
-
-    if context
-        .get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
-        return Ok(program);
-    }
+// Matches the canvas backing store to its on-screen size in device pixels so
+// HiDPI screens don't get a blurry, upscaled image. Returns the new size when
+// it changed, so callers can update their viewport and projection.
+pub fn resize_canvas(canvas: &HtmlCanvasElement) -> Option<(u32, u32)> {
+    let device_pixel_ratio = window().unwrap().device_pixel_ratio();
 
-    return Err(context.get_program_info_log(&program).unwrap_or_else(|| {
-        String::from("Unknown error creating program object")
-    }));
-}
+    let pixel_width =
+        (canvas.client_width() as f64 * device_pixel_ratio).round() as u32;
+    let pixel_height =
+        (canvas.client_height() as f64 * device_pixel_ratio).round() as u32;
 
-pub fn clear_context(context: &WebGl2RenderingContext) {
-    context.clear_color(0.0, 0.0, 0.0, 1.0);
-    context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
-    context.clear(WebGl2RenderingContext::DEPTH_BUFFER_BIT);
-}
+    if pixel_width == canvas.width() && pixel_height == canvas.height() {
+        return None;
+    }
 
-pub fn request_animation_frame(f: &Closure<dyn FnMut()>) {
-    window()
-        .unwrap()
-        .request_animation_frame(f.as_ref().unchecked_ref())
-        .unwrap();
+    canvas.set_width(pixel_width);
+    canvas.set_height(pixel_height);
+
+    return Some((pixel_width, pixel_height));
 }
 
-pub fn resize_canvas(
+// Puts a message where the canvas was, for when nothing can be drawn
+pub fn show_error(
+    document: &Document,
     canvas: &HtmlCanvasElement,
-    context: &WebGl2RenderingContext,
+    message: &str,
 ) {
-    let pixel_width = canvas.client_width();
-    let pixel_height = canvas.client_height();
-
-    if pixel_width as u32 != canvas.width() ||
-        pixel_height as u32 != canvas.height()
-    {
-        canvas.set_width(pixel_width as u32);
-        canvas.set_height(pixel_height as u32);
-        context.viewport(0, 0, 800, 800);
-    }
+    let Ok(element) = document.create_element("div") else {
+        return;
+    };
+    element.set_class_name("error");
+    element.set_text_content(Some(message));
+
+    let _ = canvas.replace_with_with_node_1(&element);
 }
 
+// Calls `handler` with the input whenever its value changes. Fails when the
+// page has no input with that id.
 pub fn create_input_handler_f32(
     document: &Document,
     id: &str,
-    handler: Box<dyn FnMut(InputEvent)>,
-) {
-    let js_closure = Closure::<dyn FnMut(InputEvent)>::new(handler);
-    let input: HtmlInputElement =
-        document.get_element_by_id(id).unwrap().dyn_into().unwrap();
+    mut handler: Box<dyn FnMut(&HtmlInputElement)>,
+) -> Result<(), SpatioError> {
+    let input: HtmlInputElement = document
+        .get_element_by_id(id)
+        .and_then(|element| element.dyn_into().ok())
+        .ok_or_else(|| SpatioError::Dom(format!("No \"{}\" input", id)))?;
+
+    let target = input.clone();
+    let js_closure =
+        Closure::<dyn FnMut(InputEvent)>::new(move |_: InputEvent| {
+            handler(&target)
+        });
     input.set_oninput(Some(js_closure.as_ref().unchecked_ref()));
     js_closure.forget();
+
+    return Ok(());
+}
+
+pub fn create_mouse_handler(
+    canvas: &HtmlCanvasElement,
+    event_type: &str,
+    handler: Box<dyn FnMut(MouseEvent)>,
+) -> Result<(), SpatioError> {
+    let js_closure = Closure::<dyn FnMut(MouseEvent)>::new(handler);
+    canvas.add_event_listener_with_callback(
+        event_type,
+        js_closure.as_ref().unchecked_ref(),
+    )?;
+    js_closure.forget();
+
+    return Ok(());
+}
+
+// Mouse position in drawing buffer pixels, the unit `Camera` works in
+pub fn canvas_mouse_position(
+    canvas: &HtmlCanvasElement,
+    event: &MouseEvent,
+) -> (f32, f32) {
+    let scale_x = canvas.width() as f32 / canvas.client_width().max(1) as f32;
+    let scale_y = canvas.height() as f32 / canvas.client_height().max(1) as f32;
+
+    return (
+        event.offset_x() as f32 * scale_x,
+        event.offset_y() as f32 * scale_y,
+    );
 }
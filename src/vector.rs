@@ -0,0 +1,448 @@
+use std::ops::{
+    Add,
+    AddAssign,
+    Div,
+    DivAssign,
+    Mul,
+    MulAssign,
+    Neg,
+    Sub,
+    SubAssign,
+};
+
+use crate::matrix::Vector4F;
+
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+pub struct Vertex {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+#[allow(dead_code)]
+impl Vertex {
+    pub fn new(x: f32, y: f32, z: f32) -> Vertex {
+        Vertex {
+            x,
+            y,
+            z,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl Vec3 {
+    pub const ZERO: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+    pub const ONE: Vec3 = Vec3::new(1.0, 1.0, 1.0);
+    pub const X: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+    pub const Y: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    pub const Z: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, z: f32) -> Vec3 {
+        Vec3 {
+            x,
+            y,
+            z,
+        }
+    }
+
+    pub fn dot(self, other: Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn distance(self, other: Vec3) -> f32 {
+        (other - self).length()
+    }
+
+    // Returns the zero vector unchanged rather than filling it with NaNs
+    pub fn normalize(self) -> Vec3 {
+        let length = self.length();
+        if length == 0.0 {
+            return self;
+        }
+        return self / length;
+    }
+
+    pub fn lerp(self, other: Vec3, t: f32) -> Vec3 {
+        self + (other - self) * t
+    }
+
+    pub fn extend(self, w: f32) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
+
+    pub fn to_array(self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+#[allow(dead_code)]
+impl Vec4 {
+    pub const ZERO: Vec4 = Vec4::new(0.0, 0.0, 0.0, 0.0);
+
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Vec4 {
+        Vec4 {
+            x,
+            y,
+            z,
+            w,
+        }
+    }
+
+    pub fn dot(self, other: Vec4) -> f32 {
+        self.x * other.x +
+            self.y * other.y +
+            self.z * other.z +
+            self.w * other.w
+    }
+
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn normalize(self) -> Vec4 {
+        let length = self.length();
+        if length == 0.0 {
+            return self;
+        }
+        return self / length;
+    }
+
+    pub fn lerp(self, other: Vec4, t: f32) -> Vec4 {
+        self + (other - self) * t
+    }
+
+    pub fn xyz(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    // Divides by w to go from homogeneous (clip) space back to 3D
+    pub fn perspective_divide(self) -> Vec3 {
+        self.xyz() / self.w
+    }
+
+    pub fn to_array(self) -> Vector4F {
+        [self.x, self.y, self.z, self.w]
+    }
+}
+
+impl From<Vertex> for Vec3 {
+    fn from(vertex: Vertex) -> Vec3 {
+        Vec3::new(vertex.x, vertex.y, vertex.z)
+    }
+}
+
+impl From<Vec3> for Vertex {
+    fn from(vec: Vec3) -> Vertex {
+        Vertex::new(vec.x, vec.y, vec.z)
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    fn from(array: [f32; 3]) -> Vec3 {
+        Vec3::new(array[0], array[1], array[2])
+    }
+}
+
+impl From<Vec3> for [f32; 3] {
+    fn from(vec: Vec3) -> [f32; 3] {
+        vec.to_array()
+    }
+}
+
+impl From<Vector4F> for Vec4 {
+    fn from(array: Vector4F) -> Vec4 {
+        Vec4::new(array[0], array[1], array[2], array[3])
+    }
+}
+
+impl From<Vec4> for Vector4F {
+    fn from(vec: Vec4) -> Vector4F {
+        vec.to_array()
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Vec3;
+
+    fn add(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+// Component-wise product
+impl Mul for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x * other.x, self.y * other.y, self.z * other.z)
+    }
+}
+
+impl Mul<f32> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, scalar: f32) -> Vec3 {
+        Vec3::new(self.x * scalar, self.y * scalar, self.z * scalar)
+    }
+}
+
+impl Mul<Vec3> for f32 {
+    type Output = Vec3;
+
+    fn mul(self, vec: Vec3) -> Vec3 {
+        vec * self
+    }
+}
+
+impl Div<f32> for Vec3 {
+    type Output = Vec3;
+
+    fn div(self, scalar: f32) -> Vec3 {
+        Vec3::new(self.x / scalar, self.y / scalar, self.z / scalar)
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl AddAssign for Vec3 {
+    fn add_assign(&mut self, other: Vec3) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Vec3 {
+    fn sub_assign(&mut self, other: Vec3) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign<f32> for Vec3 {
+    fn mul_assign(&mut self, scalar: f32) {
+        *self = *self * scalar;
+    }
+}
+
+impl DivAssign<f32> for Vec3 {
+    fn div_assign(&mut self, scalar: f32) {
+        *self = *self / scalar;
+    }
+}
+
+impl Add for Vec4 {
+    type Output = Vec4;
+
+    fn add(self, other: Vec4) -> Vec4 {
+        Vec4::new(
+            self.x + other.x,
+            self.y + other.y,
+            self.z + other.z,
+            self.w + other.w,
+        )
+    }
+}
+
+impl Sub for Vec4 {
+    type Output = Vec4;
+
+    fn sub(self, other: Vec4) -> Vec4 {
+        Vec4::new(
+            self.x - other.x,
+            self.y - other.y,
+            self.z - other.z,
+            self.w - other.w,
+        )
+    }
+}
+
+// Component-wise product
+impl Mul for Vec4 {
+    type Output = Vec4;
+
+    fn mul(self, other: Vec4) -> Vec4 {
+        Vec4::new(
+            self.x * other.x,
+            self.y * other.y,
+            self.z * other.z,
+            self.w * other.w,
+        )
+    }
+}
+
+impl Mul<f32> for Vec4 {
+    type Output = Vec4;
+
+    fn mul(self, scalar: f32) -> Vec4 {
+        Vec4::new(
+            self.x * scalar,
+            self.y * scalar,
+            self.z * scalar,
+            self.w * scalar,
+        )
+    }
+}
+
+impl Mul<Vec4> for f32 {
+    type Output = Vec4;
+
+    fn mul(self, vec: Vec4) -> Vec4 {
+        vec * self
+    }
+}
+
+impl Div<f32> for Vec4 {
+    type Output = Vec4;
+
+    fn div(self, scalar: f32) -> Vec4 {
+        Vec4::new(
+            self.x / scalar,
+            self.y / scalar,
+            self.z / scalar,
+            self.w / scalar,
+        )
+    }
+}
+
+impl Neg for Vec4 {
+    type Output = Vec4;
+
+    fn neg(self) -> Vec4 {
+        Vec4::new(-self.x, -self.y, -self.z, -self.w)
+    }
+}
+
+impl AddAssign for Vec4 {
+    fn add_assign(&mut self, other: Vec4) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Vec4 {
+    fn sub_assign(&mut self, other: Vec4) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign<f32> for Vec4 {
+    fn mul_assign(&mut self, scalar: f32) {
+        *self = *self * scalar;
+    }
+}
+
+impl DivAssign<f32> for Vec4 {
+    fn div_assign(&mut self, scalar: f32) {
+        *self = *self / scalar;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cross_is_right_handed() {
+        assert_eq!(Vec3::X.cross(Vec3::Y), Vec3::Z);
+        assert_eq!(Vec3::Y.cross(Vec3::Z), Vec3::X);
+        assert_eq!(Vec3::Z.cross(Vec3::X), Vec3::Y);
+        assert_eq!(Vec3::Y.cross(Vec3::X), -Vec3::Z);
+    }
+
+    #[test]
+    fn length_and_normalize() {
+        let v = Vec3::new(2.0, -3.0, 6.0);
+        assert_eq!(v.length(), 7.0);
+        assert_eq!(v.normalize(), v / 7.0);
+        assert_eq!(Vec3::ZERO.normalize(), Vec3::ZERO);
+        assert_eq!(Vec3::X.distance(Vec3::new(1.0, 3.0, 4.0)), 5.0);
+
+        let w = Vec4::new(1.0, 1.0, 1.0, 1.0);
+        assert_eq!(w.length(), 2.0);
+        assert_eq!(w.normalize(), Vec4::new(0.5, 0.5, 0.5, 0.5));
+        assert_eq!(Vec4::ZERO.normalize(), Vec4::ZERO);
+    }
+
+    #[test]
+    fn operators_are_component_wise() {
+        let a = Vec3::new(1.0, 2.0, 3.0);
+        let b = Vec3::new(4.0, 5.0, 6.0);
+        assert_eq!(a + b, Vec3::new(5.0, 7.0, 9.0));
+        assert_eq!(b - a, Vec3::new(3.0, 3.0, 3.0));
+        assert_eq!(a * b, Vec3::new(4.0, 10.0, 18.0));
+        assert_eq!(a * 2.0, 2.0 * a);
+        assert_eq!(a.dot(b), 32.0);
+
+        let c = Vec4::new(2.0, 4.0, 6.0, 8.0);
+        assert_eq!(c / 2.0, Vec4::new(1.0, 2.0, 3.0, 4.0));
+        assert_eq!(c * 0.5, c / 2.0);
+        assert_eq!(-c + c, Vec4::ZERO);
+
+        let mut d = c;
+        d /= 2.0;
+        d *= 4.0;
+        d -= c;
+        assert_eq!(d, c);
+    }
+
+    #[test]
+    fn lerp_and_perspective_divide() {
+        let a = Vec3::ZERO;
+        let b = Vec3::new(2.0, 4.0, -2.0);
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert_eq!(a.lerp(b, 0.5), Vec3::new(1.0, 2.0, -1.0));
+
+        let clip = Vec4::new(2.0, 4.0, 6.0, 2.0);
+        assert_eq!(clip.perspective_divide(), Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(b.extend(1.0).xyz(), b);
+    }
+}
@@ -0,0 +1,671 @@
+use std::collections::HashMap;
+
+use web_sys::{
+    WebGl2RenderingContext,
+    WebGlBuffer,
+    WebGlProgram,
+    WebGlRenderingContext,
+    WebGlShader,
+    WebGlTexture,
+    WebGlUniformLocation,
+};
+
+use crate::backend::{
+    Backend,
+    BufferData,
+    BufferHandle,
+    DrawMode,
+    Face,
+    ProgramHandle,
+    ProgramInfo,
+    ProgramKind,
+    TextureData,
+    TextureHandle,
+    Uniform,
+    UniformType,
+};
+use crate::colour::Colour;
+use crate::error::SpatioError;
+
+// Locations of the program's active uniforms and attributes, looked up once
+// at link time
+struct ProgramEntry {
+    program:    WebGlProgram,
+    info:       ProgramInfo,
+    uniforms:   HashMap<String, WebGlUniformLocation>,
+    attributes: HashMap<String, u32>,
+}
+
+// Array uniforms are reported as `name[0]`. Every element gets its own
+// location, and `name` is the first one's.
+fn active_names(name: String, size: i32) -> Vec<String> {
+    let Some(name) = name.strip_suffix("[0]") else {
+        return vec![name];
+    };
+
+    let mut names = vec![name.to_string()];
+    names.extend((0..size).map(|i| format!("{}[{}]", name, i)));
+    return names;
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// Replaces whole identifiers only, so `colour` doesn't touch `colours`
+fn replace_identifier(source: &str, from: &str, to: &str) -> String {
+    let mut result = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(i) = rest.find(from) {
+        let before = rest[..i].chars().next_back();
+        let after = rest[i + from.len()..].chars().next();
+        let whole = !before.is_some_and(is_identifier) &&
+            !after.is_some_and(is_identifier);
+
+        result.push_str(&rest[..i]);
+        result.push_str(if whole { to } else { from });
+        rest = &rest[i + from.len()..];
+    }
+
+    result.push_str(rest);
+    return result;
+}
+
+// Rewrites a GLSL ES 3.00 shader as GLSL ES 1.00 for WebGL1. Only covers what
+// our shaders use: `in` / `out` qualifiers, a single fragment output and
+// `texture()`.
+pub fn downgrade_shader(source: &str, shader_type: u32) -> String {
+    let fragment = shader_type == WebGlRenderingContext::FRAGMENT_SHADER;
+    let mut output = None;
+    let mut lines = vec![];
+
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("#version") {
+            continue;
+        } else if let Some(rest) = trimmed.strip_prefix("in ") {
+            let qualifier = if fragment { "varying" } else { "attribute" };
+            lines.push(format!("{} {}", qualifier, rest));
+        } else if let Some(rest) = trimmed.strip_prefix("out ") {
+            if fragment {
+                output = rest
+                    .split_whitespace()
+                    .nth(1)
+                    .map(|name| name.trim_end_matches(';').to_string());
+            } else {
+                lines.push(format!("varying {}", rest));
+            }
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+
+    let mut source =
+        replace_identifier(&lines.join("\n"), "texture", "texture2D");
+    if let Some(output) = output {
+        source = replace_identifier(&source, &output, "gl_FragColor");
+    }
+    return source;
+}
+
+// The WebGL1 and WebGL2 contexts share nearly all of their API but not a
+// type, so both backends come from the same code. Only the names of the
+// texture uploads from DOM elements differ.
+macro_rules! webgl_backend {
+    (
+        $backend:ident,
+        $context:ty,
+        $prepare_shader:expr,
+        $upload_image:ident,
+        $upload_canvas:ident
+    ) => {
+        pub struct $backend {
+            context:  $context,
+            buffers:  Vec<Option<WebGlBuffer>>,
+            textures: Vec<Option<WebGlTexture>>,
+            programs: Vec<ProgramEntry>,
+            current:  Option<usize>,
+        }
+
+        #[allow(dead_code)]
+        impl $backend {
+            // Depth testing is on until turned off. Blending and the depth
+            // offset are off until asked for.
+            pub fn new(context: $context) -> $backend {
+                context.enable(<$context>::DEPTH_TEST);
+                context.depth_func(<$context>::LEQUAL);
+                context.blend_func_separate(
+                    <$context>::SRC_ALPHA,
+                    <$context>::ONE_MINUS_SRC_ALPHA,
+                    <$context>::ONE,
+                    <$context>::ONE_MINUS_SRC_ALPHA,
+                );
+                context.polygon_offset(1.0, 1.0);
+
+                $backend {
+                    context,
+                    buffers: vec![],
+                    textures: vec![],
+                    programs: vec![],
+                    current: None,
+                }
+            }
+
+            pub fn context(&self) -> &$context {
+                &self.context
+            }
+
+            pub fn compile_shader(
+                &self,
+                shader_type: u32,
+                source: &str,
+            ) -> Result<WebGlShader, SpatioError> {
+                let source = $prepare_shader(source, shader_type);
+
+                let shader = self
+                    .context
+                    .create_shader(shader_type)
+                    .ok_or_else(|| {
+                        SpatioError::ShaderCompile(String::from(
+                            "Unable to create shader object",
+                        ))
+                    })?;
+                self.context.shader_source(&shader, &source);
+                self.context.compile_shader(&shader);
+
+                if self
+                    .context
+                    .get_shader_parameter(&shader, <$context>::COMPILE_STATUS)
+                    .as_bool()
+                    .unwrap_or(false)
+                {
+                    return Ok(shader);
+                }
+
+                let log = self.context.get_shader_info_log(&shader);
+                self.context.delete_shader(Some(&shader));
+                return Err(SpatioError::ShaderCompile(log.unwrap_or_else(
+                    || String::from("Unknown error creating shader"),
+                )));
+            }
+
+            pub fn link_program(
+                &self,
+                vert_shader: &WebGlShader,
+                frag_shader: &WebGlShader,
+            ) -> Result<WebGlProgram, SpatioError> {
+                let program =
+                    self.context.create_program().ok_or_else(|| {
+                        SpatioError::ProgramLink(String::from(
+                            "Unable to create program",
+                        ))
+                    })?;
+                self.context.attach_shader(&program, vert_shader);
+                self.context.attach_shader(&program, frag_shader);
+                self.context.link_program(&program);
+
+                if self
+                    .context
+                    .get_program_parameter(&program, <$context>::LINK_STATUS)
+                    .as_bool()
+                    .unwrap_or(false)
+                {
+                    return Ok(program);
+                }
+
+                let log = self.context.get_program_info_log(&program);
+                self.context.delete_program(Some(&program));
+                return Err(SpatioError::ProgramLink(log.unwrap_or_else(
+                    || String::from("Unknown error creating program object"),
+                )));
+            }
+
+            fn reflect(&self, program: WebGlProgram) -> ProgramEntry {
+                let mut entry = ProgramEntry {
+                    info: ProgramInfo::default(),
+                    uniforms: HashMap::new(),
+                    attributes: HashMap::new(),
+                    program,
+                };
+                let count = |parameter| {
+                    self.context
+                        .get_program_parameter(&entry.program, parameter)
+                        .as_f64()
+                        .unwrap_or(0.0) as u32
+                };
+                let uniform_count = count(<$context>::ACTIVE_UNIFORMS);
+                let attribute_count = count(<$context>::ACTIVE_ATTRIBUTES);
+
+                for i in 0..uniform_count {
+                    let Some(active) =
+                        self.context.get_active_uniform(&entry.program, i)
+                    else {
+                        continue;
+                    };
+                    let uniform_type = match active.type_() {
+                        <$context>::INT => UniformType::Int,
+                        <$context>::BOOL => UniformType::Bool,
+                        <$context>::FLOAT => UniformType::Float,
+                        <$context>::FLOAT_VEC2 => UniformType::Vec2,
+                        <$context>::FLOAT_VEC3 => UniformType::Vec3,
+                        <$context>::FLOAT_VEC4 => UniformType::Vec4,
+                        <$context>::FLOAT_MAT3 => UniformType::Mat3,
+                        <$context>::FLOAT_MAT4 => UniformType::Mat4,
+                        <$context>::SAMPLER_2D => UniformType::Sampler2D,
+                        _ => continue,
+                    };
+
+                    for name in active_names(active.name(), active.size()) {
+                        let Some(location) = self
+                            .context
+                            .get_uniform_location(&entry.program, &name)
+                        else {
+                            continue;
+                        };
+
+                        entry.info.uniforms.insert(name.clone(), uniform_type);
+                        entry.uniforms.insert(name, location);
+                    }
+                }
+
+                for i in 0..attribute_count {
+                    let Some(active) =
+                        self.context.get_active_attrib(&entry.program, i)
+                    else {
+                        continue;
+                    };
+                    let name = active.name();
+                    let location =
+                        self.context.get_attrib_location(&entry.program, &name);
+                    let Ok(location) = u32::try_from(location) else {
+                        continue;
+                    };
+
+                    entry.info.attributes.insert(name.clone());
+                    entry.attributes.insert(name, location);
+                }
+
+                return entry;
+            }
+
+            fn attribute_location(&self, name: &str) -> Option<u32> {
+                let entry = self.programs.get(self.current?)?;
+                return entry.attributes.get(name).copied();
+            }
+
+            fn uniform_location(
+                &self,
+                name: &str,
+            ) -> Option<&WebGlUniformLocation> {
+                let entry = self.programs.get(self.current?)?;
+                return entry.uniforms.get(name);
+            }
+        }
+
+        impl Backend for $backend {
+            fn create_buffer(
+                &mut self,
+                data: BufferData,
+            ) -> Result<BufferHandle, SpatioError> {
+                let buffer = self
+                    .context
+                    .create_buffer()
+                    .ok_or(SpatioError::BufferCreation)?;
+
+                // Views into wasm memory are only valid until the next
+                // allocation, so they're handed straight to GL
+                unsafe {
+                    match data {
+                        BufferData::Vertices(data) => {
+                            self.context.bind_buffer(
+                                <$context>::ARRAY_BUFFER,
+                                Some(&buffer),
+                            );
+                            self.context.buffer_data_with_array_buffer_view(
+                                <$context>::ARRAY_BUFFER,
+                                &js_sys::Float32Array::view(data),
+                                <$context>::STATIC_DRAW,
+                            );
+                        }
+                        BufferData::Indices(data) => {
+                            self.context.bind_buffer(
+                                <$context>::ELEMENT_ARRAY_BUFFER,
+                                Some(&buffer),
+                            );
+                            self.context.buffer_data_with_array_buffer_view(
+                                <$context>::ELEMENT_ARRAY_BUFFER,
+                                &js_sys::Uint16Array::view(data),
+                                <$context>::STATIC_DRAW,
+                            );
+                        }
+                    }
+                }
+
+                self.buffers.push(Some(buffer));
+                return Ok(BufferHandle(self.buffers.len() as u32 - 1));
+            }
+
+            fn delete_buffer(&mut self, buffer: BufferHandle) {
+                let slot = self.buffers.get_mut(buffer.0 as usize);
+                if let Some(buffer) = slot.and_then(|slot| slot.take()) {
+                    self.context.delete_buffer(Some(&buffer));
+                }
+            }
+
+            fn create_texture(
+                &mut self,
+                data: TextureData,
+            ) -> Result<TextureHandle, SpatioError> {
+                data.check()?;
+
+                let texture = self.context.create_texture().ok_or_else(|| {
+                    SpatioError::TextureCreation(String::from(
+                        "Unable to create texture object",
+                    ))
+                })?;
+                let target = <$context>::TEXTURE_2D;
+                let rgba = <$context>::RGBA;
+                let byte = <$context>::UNSIGNED_BYTE;
+
+                self.context.bind_texture(target, Some(&texture));
+                // GL's first row is the bottom one
+                self.context.pixel_storei(<$context>::UNPACK_FLIP_Y_WEBGL, 1);
+                let uploaded = match data {
+                    TextureData::Rgba {
+                        width,
+                        height,
+                        pixels,
+                    } => self.context
+                        .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                            target,
+                            0,
+                            rgba as i32,
+                            width as i32,
+                            height as i32,
+                            0,
+                            rgba,
+                            byte,
+                            Some(pixels),
+                        ),
+                    TextureData::HtmlImage(image) => self
+                        .context
+                        .$upload_image(target, 0, rgba as i32, rgba, byte, image),
+                    TextureData::Canvas(canvas) => self
+                        .context
+                        .$upload_canvas(target, 0, rgba as i32, rgba, byte, canvas),
+                };
+                self.context.pixel_storei(<$context>::UNPACK_FLIP_Y_WEBGL, 0);
+
+                if let Err(error) = uploaded {
+                    self.context.delete_texture(Some(&texture));
+                    return Err(SpatioError::TextureCreation(
+                        error.as_string().unwrap_or_else(|| format!("{:?}", error)),
+                    ));
+                }
+
+                // WebGL1 can only mipmap power of two textures, and clamp
+                // the others
+                let (width, height) = data.size();
+                let min_filter =
+                    if width.is_power_of_two() && height.is_power_of_two() {
+                        self.context.generate_mipmap(target);
+                        <$context>::LINEAR_MIPMAP_LINEAR
+                    } else {
+                        <$context>::LINEAR
+                    };
+                let parameters = [
+                    (<$context>::TEXTURE_MIN_FILTER, min_filter),
+                    (<$context>::TEXTURE_MAG_FILTER, <$context>::LINEAR),
+                    (<$context>::TEXTURE_WRAP_S, <$context>::CLAMP_TO_EDGE),
+                    (<$context>::TEXTURE_WRAP_T, <$context>::CLAMP_TO_EDGE),
+                ];
+                for (name, value) in parameters {
+                    self.context.tex_parameteri(target, name, value as i32);
+                }
+
+                self.textures.push(Some(texture));
+                return Ok(TextureHandle(self.textures.len() as u32 - 1));
+            }
+
+            fn delete_texture(&mut self, texture: TextureHandle) {
+                let slot = self.textures.get_mut(texture.0 as usize);
+                if let Some(texture) = slot.and_then(|slot| slot.take()) {
+                    self.context.delete_texture(Some(&texture));
+                }
+            }
+
+            fn bind_texture(&mut self, unit: u32, texture: TextureHandle) {
+                let Some(Some(texture)) = self.textures.get(texture.0 as usize)
+                else {
+                    return;
+                };
+
+                self.context.active_texture(<$context>::TEXTURE0 + unit);
+                self.context
+                    .bind_texture(<$context>::TEXTURE_2D, Some(texture));
+            }
+
+            fn create_program(
+                &mut self,
+                kind: ProgramKind,
+                vertex_source: &str,
+                fragment_source: &str,
+            ) -> Result<ProgramHandle, SpatioError> {
+                let vert_shader = self
+                    .compile_shader(<$context>::VERTEX_SHADER, vertex_source)?;
+                let frag_shader = match self.compile_shader(
+                    <$context>::FRAGMENT_SHADER,
+                    fragment_source,
+                ) {
+                    Ok(shader) => shader,
+                    Err(error) => {
+                        self.context.delete_shader(Some(&vert_shader));
+                        return Err(error);
+                    }
+                };
+                let program = self.link_program(&vert_shader, &frag_shader);
+
+                // Linked or not, the shaders aren't needed anymore. Attached
+                // ones are only flagged and go with the program.
+                self.context.delete_shader(Some(&vert_shader));
+                self.context.delete_shader(Some(&frag_shader));
+                let program = program?;
+
+                let mut entry = self.reflect(program);
+                entry.info.kind = kind;
+                self.programs.push(entry);
+                return Ok(ProgramHandle(self.programs.len() as u32 - 1));
+            }
+
+            fn use_program(&mut self, program: ProgramHandle) {
+                let index = program.0 as usize;
+                if let Some(entry) = self.programs.get(index) {
+                    self.context.use_program(Some(&entry.program));
+                    self.current = Some(index);
+                }
+            }
+
+            fn program_info(
+                &self,
+                program: ProgramHandle,
+            ) -> Option<ProgramInfo> {
+                let entry = self.programs.get(program.0 as usize)?;
+                return Some(entry.info.clone());
+            }
+
+            fn bind_attribute(
+                &mut self,
+                name: &str,
+                buffer: BufferHandle,
+                size: u32,
+            ) {
+                let Some(location) = self.attribute_location(name) else {
+                    return;
+                };
+                let Some(Some(buffer)) = self.buffers.get(buffer.0 as usize)
+                else {
+                    return;
+                };
+
+                self.context
+                    .bind_buffer(<$context>::ARRAY_BUFFER, Some(buffer));
+                self.context.enable_vertex_attrib_array(location);
+                self.context.vertex_attrib_pointer_with_i32(
+                    location,
+                    size as i32,
+                    <$context>::FLOAT,
+                    false,
+                    0,
+                    0,
+                );
+            }
+
+            fn disable_attribute(&mut self, name: &str) {
+                if let Some(location) = self.attribute_location(name) {
+                    self.context.disable_vertex_attrib_array(location);
+                }
+            }
+
+            fn set_uniform(&mut self, name: &str, value: Uniform) {
+                let location = self.uniform_location(name);
+                if location.is_none() {
+                    return;
+                }
+
+                match value {
+                    Uniform::Int(value) => {
+                        self.context.uniform1i(location, value)
+                    }
+                    Uniform::Float(value) => {
+                        self.context.uniform1f(location, value)
+                    }
+                    Uniform::Vec2([x, y]) => {
+                        self.context.uniform2f(location, x, y)
+                    }
+                    Uniform::Vec3(value) => self
+                        .context
+                        .uniform3f(location, value.x, value.y, value.z),
+                    Uniform::Vec4([x, y, z, w]) => {
+                        self.context.uniform4f(location, x, y, z, w)
+                    }
+                    Uniform::Mat3(value) => {
+                        self.context.uniform_matrix3fv_with_f32_array(
+                            location, false, &value,
+                        )
+                    }
+                    Uniform::Mat4(value) => {
+                        self.context.uniform_matrix4fv_with_f32_array(
+                            location, false, &value,
+                        )
+                    }
+                }
+            }
+
+            fn draw(
+                &mut self,
+                mode: DrawMode,
+                indices: Option<BufferHandle>,
+                count: usize,
+            ) {
+                let mode = match mode {
+                    DrawMode::Triangles => <$context>::TRIANGLES,
+                    DrawMode::Lines => <$context>::LINES,
+                };
+
+                match indices {
+                    Some(indices) => {
+                        let Some(Some(buffer)) =
+                            self.buffers.get(indices.0 as usize)
+                        else {
+                            return;
+                        };
+                        self.context.bind_buffer(
+                            <$context>::ELEMENT_ARRAY_BUFFER,
+                            Some(buffer),
+                        );
+                        self.context.draw_elements_with_i32(
+                            mode,
+                            count as i32,
+                            <$context>::UNSIGNED_SHORT,
+                            0,
+                        );
+                    }
+                    None => self.context.draw_arrays(mode, 0, count as i32),
+                }
+            }
+
+            fn set_viewport(&mut self, width: u32, height: u32) {
+                self.context.viewport(0, 0, width as i32, height as i32);
+            }
+
+            fn set_blending(&mut self, enabled: bool) {
+                if enabled {
+                    self.context.enable(<$context>::BLEND);
+                } else {
+                    self.context.disable(<$context>::BLEND);
+                }
+            }
+
+            fn set_depth_write(&mut self, enabled: bool) {
+                self.context.depth_mask(enabled);
+            }
+
+            fn set_depth_offset(&mut self, enabled: bool) {
+                if enabled {
+                    self.context.enable(<$context>::POLYGON_OFFSET_FILL);
+                } else {
+                    self.context.disable(<$context>::POLYGON_OFFSET_FILL);
+                }
+            }
+
+            fn set_depth_test(&mut self, enabled: bool) {
+                if enabled {
+                    self.context.enable(<$context>::DEPTH_TEST);
+                } else {
+                    self.context.disable(<$context>::DEPTH_TEST);
+                }
+            }
+
+            fn set_face_culling(&mut self, face: Option<Face>) {
+                match face {
+                    Some(face) => {
+                        self.context.enable(<$context>::CULL_FACE);
+                        self.context.cull_face(match face {
+                            Face::Front => <$context>::FRONT,
+                            Face::Back => <$context>::BACK,
+                        });
+                    }
+                    None => self.context.disable(<$context>::CULL_FACE),
+                }
+            }
+
+            fn clear(&mut self, colour: Colour) {
+                // A disabled depth mask would also keep the depth buffer
+                // from being cleared
+                self.context.depth_mask(true);
+                self.context
+                    .clear_color(colour.r, colour.g, colour.b, colour.a);
+                self.context.clear(
+                    <$context>::COLOR_BUFFER_BIT | <$context>::DEPTH_BUFFER_BIT,
+                );
+            }
+        }
+    };
+}
+
+webgl_backend!(
+    WebGl2Backend,
+    WebGl2RenderingContext,
+    |source: &str, _| source.to_string(),
+    tex_image_2d_with_u32_and_u32_and_html_image_element,
+    tex_image_2d_with_u32_and_u32_and_html_canvas_element
+);
+webgl_backend!(
+    WebGl1Backend,
+    WebGlRenderingContext,
+    downgrade_shader,
+    tex_image_2d_with_u32_and_u32_and_image,
+    tex_image_2d_with_u32_and_u32_and_canvas
+);
@@ -0,0 +1,125 @@
+// Renders scenes with the software backend and compares them against the
+// PNGs in tests/reference. Built with the `png` feature only. When a scene
+// changes on purpose, rerun with SPATIO_UPDATE_REFERENCES=1 to write the new
+// reference, and check it before committing it.
+
+// Explicit returns, like the crate
+#![allow(clippy::needless_return)]
+
+use std::env;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use spatio::ball::Ball;
+use spatio::camera::Camera;
+use spatio::cartesian_axis::CartesianAxis;
+use spatio::colour::Colour;
+use spatio::image::Image;
+use spatio::material::Material;
+use spatio::program::Program;
+use spatio::scene::{Scene, SceneNode};
+use spatio::software::SoftwareRenderer;
+use spatio::vector::{Vec3, Vertex};
+
+const SIZE: u32 = 256;
+// Channels may be off by this much, for rounding
+const TOLERANCE: u8 = 2;
+// And this many pixels by more, for edges landing on the other side of a
+// pixel centre
+const MAX_DIFFERENT_PIXELS: usize = 16;
+
+fn reference_path(name: &str) -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("reference")
+        .join(format!("{}.png", name));
+}
+
+// Black where the images match, the difference scaled up elsewhere
+fn difference(a: &Image, b: &Image) -> (Image, usize) {
+    let mut diff = Image::new(a.width, a.height);
+    let mut different = 0;
+
+    for y in 0..a.height {
+        for x in 0..a.width {
+            let (pa, pb) = (a.get(x, y), b.get(x, y));
+            let channel = |i: usize| pa[i].abs_diff(pb[i]);
+            let largest = (0..4).map(channel).max().unwrap_or(0);
+            if largest > TOLERANCE {
+                different += 1;
+            }
+
+            let scaled = |i: usize| channel(i).saturating_mul(4);
+            diff.set(x, y, [scaled(0), scaled(1), scaled(2), 255]);
+        }
+    }
+
+    return (diff, different);
+}
+
+fn assert_matches_reference(name: &str, image: &Image) {
+    let path = reference_path(name);
+    if env::var_os("SPATIO_UPDATE_REFERENCES").is_some() {
+        image.save_png(&path).unwrap();
+        return;
+    }
+
+    let reference = Image::load_png(&path).unwrap_or_else(|error| {
+        panic!(
+            "Can't load {} ({}), run with SPATIO_UPDATE_REFERENCES=1 to \
+             write it",
+            path.display(),
+            error
+        )
+    });
+    let size_matches = image.max_difference(&reference).is_some();
+    assert!(size_matches, "{} isn't {}x{}", path.display(), SIZE, SIZE);
+
+    let (diff, different) = difference(image, &reference);
+    if different > MAX_DIFFERENT_PIXELS {
+        let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+        let actual_path = out.join(format!("{}.png", name));
+        let diff_path = out.join(format!("{}-diff.png", name));
+        image.save_png(&actual_path).unwrap();
+        diff.save_png(&diff_path).unwrap();
+
+        panic!(
+            "{} pixels differ from {}, see {} and {}",
+            different,
+            path.display(),
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn ball_and_axes() {
+    let mut backend = SoftwareRenderer::new(SIZE, SIZE);
+    let program = Rc::new(Program::standard(&mut backend).unwrap());
+
+    // Like the demo, the axes show through the translucent ball
+    let ball = Ball::new(
+        &mut backend,
+        Vertex::new(0.0, 0.0, 0.0),
+        0.6,
+        24,
+        Rc::new(
+            Material::new(program.clone(), Colour::RED.with_alpha(0.4))
+                .unwrap(),
+        ),
+    )
+    .unwrap();
+    let axes = CartesianAxis::new(&mut backend, program).unwrap();
+
+    let mut scene = Scene::new();
+    scene.add(SceneNode::with_drawable(Rc::new(ball)));
+    scene.add(axes.into_node());
+    scene.update_world_matrices();
+
+    let mut camera = Camera::new(Vec3::new(1.5, 1.2, 2.5), Vec3::ZERO);
+    camera.set_viewport(SIZE, SIZE);
+    scene.draw(&mut backend, &camera).unwrap();
+
+    assert_matches_reference("ball_and_axes", &backend.image());
+}